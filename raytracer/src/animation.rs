@@ -0,0 +1,193 @@
+use std::ops::Range;
+use std::path::Path;
+use std::sync::Arc;
+
+use crate::camera::Camera;
+use crate::hdr::HdrImage;
+use crate::tonemap::ToneMapping;
+use crate::util::vec3::{Point3, Vec3};
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Interpolation {
+    Linear,
+    CatmullRom, // smooth curve through all keyframes
+}
+
+#[derive(Clone, Copy, Debug)]
+pub struct CameraKeyframe {
+    pub frame: f64,
+    pub lookfrom: Point3,
+    pub lookat: Point3,
+    pub vfov: f64,
+}
+
+// Keyframed camera motion. Keyframes are held before the first and after the last one.
+pub struct CameraPath {
+    keyframes: Vec<CameraKeyframe>,
+    pub interpolation: Interpolation,
+}
+
+// A camera path together with the frames to render.
+pub struct Animation {
+    pub path: CameraPath,
+    pub vup: Vec3,
+    pub frames: Range<u32>,
+}
+
+impl CameraPath {
+    pub fn new(mut keyframes: Vec<CameraKeyframe>, interpolation: Interpolation) -> Self {
+        assert!(!keyframes.is_empty(), "a camera path needs at least one keyframe");
+        keyframes.sort_by(|a, b| a.frame.partial_cmp(&b.frame).unwrap());
+        Self { keyframes, interpolation }
+    }
+
+    pub fn keyframes(&self) -> &[CameraKeyframe] {
+        &self.keyframes
+    }
+
+    pub fn sample(&self, frame: f64) -> CameraKeyframe {
+        let n = self.keyframes.len();
+        if frame <= self.keyframes[0].frame {
+            return CameraKeyframe { frame, ..self.keyframes[0] };
+        }
+        if frame >= self.keyframes[n - 1].frame {
+            return CameraKeyframe { frame, ..self.keyframes[n - 1] };
+        }
+        let k = self.keyframes.iter().rposition(|key| key.frame <= frame).unwrap();
+        let (k1, k2) = (&self.keyframes[k], &self.keyframes[k + 1]);
+        let t = (frame - k1.frame) / (k2.frame - k1.frame);
+        match self.interpolation {
+            Interpolation::Linear => CameraKeyframe {
+                frame,
+                lookfrom: lerp(k1.lookfrom, k2.lookfrom, t),
+                lookat: lerp(k1.lookat, k2.lookat, t),
+                vfov: k1.vfov * (1.0 - t) + k2.vfov * t,
+            },
+            Interpolation::CatmullRom => {
+                // the end points are repeated as outer control points
+                let k0 = &self.keyframes[k.saturating_sub(1)];
+                let k3 = &self.keyframes[(k + 2).min(n - 1)];
+                CameraKeyframe {
+                    frame,
+                    lookfrom: catmull_rom_vec3(k0.lookfrom, k1.lookfrom, k2.lookfrom, k3.lookfrom, t),
+                    lookat: catmull_rom_vec3(k0.lookat, k1.lookat, k2.lookat, k3.lookat, t),
+                    vfov: catmull_rom(k0.vfov, k1.vfov, k2.vfov, k3.vfov, t),
+                }
+            }
+        }
+    }
+}
+
+fn lerp(a: Vec3, b: Vec3, t: f64) -> Vec3 {
+    a * (1.0 - t) + b * t
+}
+
+// uniform Catmull-Rom spline between p1 (t = 0) and p2 (t = 1)
+fn catmull_rom(p0: f64, p1: f64, p2: f64, p3: f64, t: f64) -> f64 {
+    let t2 = t * t;
+    let t3 = t2 * t;
+    0.5 * (2.0 * p1
+        + (p2 - p0) * t
+        + (2.0 * p0 - 5.0 * p1 + 4.0 * p2 - p3) * t2
+        + (3.0 * p1 - p0 - 3.0 * p2 + p3) * t3)
+}
+
+fn catmull_rom_vec3(p0: Vec3, p1: Vec3, p2: Vec3, p3: Vec3, t: f64) -> Vec3 {
+    Vec3::new(
+        catmull_rom(p0.x, p1.x, p2.x, p3.x, t),
+        catmull_rom(p0.y, p1.y, p2.y, p3.y, t),
+        catmull_rom(p0.z, p1.z, p2.z, p3.z, t),
+    )
+}
+
+// the file of one frame, the frame number goes between the stem and the extension
+pub fn frame_path(path: &str, frame: u32) -> String {
+    let path = Path::new(path);
+    let stem = path.with_extension("");
+    match path.extension().and_then(|e| e.to_str()) {
+        Some(extension) => format!("{}_{:04}.{}", stem.display(), frame, extension),
+        None => format!("{}_{:04}", stem.display(), frame),
+    }
+}
+
+// Render every frame of the animation and save it to every output, e.g. `out.png` as
+// `out_0001.png` etc. Time is measured in frames: the shutter of frame f opens at f and
+// stays open as long as the camera's, so Moving objects keyframed in frames move along.
+pub fn render_sequence<F>(
+    camera: &mut Arc<Camera>,
+    animation: &Animation,
+    outputs: &[String],
+    tone_mapping: &ToneMapping,
+    render: F,
+) -> Result<(), String>
+where
+    F: Fn(&Arc<Camera>) -> HdrImage,
+{
+    let exposure = camera.shutter_close - camera.shutter_open;
+    for frame in animation.frames.clone() {
+        let key = animation.path.sample(frame as f64);
+        {
+            let camera = Arc::get_mut(camera).expect("camera is shared");
+            camera
+                .set_view(key.lookfrom, key.lookat, animation.vup, key.vfov)
+                .map_err(|e| format!("frame {}: {}", frame, e))?;
+            camera.shutter_open = frame as f64;
+            camera.shutter_close = frame as f64 + exposure;
+        }
+        let image = render(camera);
+        for output in outputs {
+            let path = frame_path(output, frame);
+            image.save(&path, tone_mapping)?;
+            println!("Saved frame {} to {}", frame, path);
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn keyframe(frame: f64, x: f64, vfov: f64) -> CameraKeyframe {
+        CameraKeyframe {
+            frame,
+            lookfrom: Point3::new(x, 0.0, 0.0),
+            lookat: Point3::new(x, 0.0, -1.0),
+            vfov,
+        }
+    }
+
+    #[test]
+    fn test_linear() {
+        let path = CameraPath::new(vec![keyframe(10.0, 1.0, 90.0), keyframe(0.0, 0.0, 60.0)], Interpolation::Linear);
+        let key = path.sample(2.5);
+        assert!((key.lookfrom.x - 0.25).abs() < 1e-12);
+        assert!((key.vfov - 67.5).abs() < 1e-12);
+        assert_eq!(path.sample(-5.0).lookfrom.x, 0.0);
+        assert_eq!(path.sample(20.0).lookfrom.x, 1.0);
+    }
+
+    #[test]
+    fn test_catmull_rom() {
+        let keyframes = vec![
+            keyframe(0.0, 0.0, 60.0),
+            keyframe(1.0, 1.0, 60.0),
+            keyframe(2.0, 3.0, 60.0),
+            keyframe(3.0, 2.0, 60.0),
+        ];
+        let path = CameraPath::new(keyframes, Interpolation::CatmullRom);
+        // passes through the keyframes
+        for key in path.keyframes() {
+            assert!((path.sample(key.frame).lookfrom.x - key.lookfrom.x).abs() < 1e-12);
+        }
+        // 0.5 * (2 * 1 + (3 - 0) * 0.5 + (0 - 5 + 12 - 2) * 0.25 + (3 - 0 - 9 + 2) * 0.125)
+        assert!((path.sample(1.5).lookfrom.x - 2.125).abs() < 1e-12);
+        assert!((path.sample(1.5).vfov - 60.0).abs() < 1e-12);
+    }
+    #[test]
+    fn test_frame_path() {
+        assert_eq!(frame_path("output/test3.png", 7), "output/test3_0007.png");
+        assert_eq!(frame_path("render.exr", 12345), "render_12345.exr");
+        assert_eq!(frame_path("frames/shot", 1), "frames/shot_0001");
+    }
+}
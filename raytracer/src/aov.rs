@@ -0,0 +1,229 @@
+// Arbitrary output variables: what the camera sees first in every pixel, besides the beauty
+// image, for compositing and denoising. Depth and the IDs come from the ray through the pixel
+// center, normals, albedo and coverage are averaged over AOV_SAMPLES rays inside the pixel
+// so they are antialiased like the beauty image.
+
+use image::{ImageBuffer, Rgb, RgbImage};
+use std::sync::Arc;
+
+use crate::camera::Camera;
+use crate::hdr::{encode_exr, HdrImage};
+use crate::render::{render_tiles, spiral_tiles, RenderSettings, Tile};
+use crate::tonemap;
+use crate::util::sampler::Sampler;
+use crate::util::vec3::{Color, Vec3};
+
+pub const AOV_SAMPLES: u32 = 8;
+
+#[derive(Clone)]
+pub struct Aovs {
+    pub width: u32,
+    pub height: u32,
+    pub depth: Vec<f32>,       // distance along the viewing direction, infinite where nothing is hit
+    pub normal: Vec<Vec3>,     // world space, facing the camera
+    pub albedo: Vec<Color>,
+    pub material_id: Vec<u32>, // shared IDs of the world first, then one per object, 0 for nothing
+    pub object_id: Vec<u32>,   // numbered from 1 in world order, 0 for nothing
+    pub alpha: Vec<f32>,       // fraction of the pixel covered by geometry
+}
+
+struct PixelAov {
+    depth: f32,
+    normal: Vec3,
+    albedo: Color,
+    material_id: u32,
+    object_id: u32,
+    alpha: f32,
+}
+
+fn sample_pixel_aov(camera: &Camera, x: u32, y: u32, sampler: &mut dyn Sampler) -> PixelAov {
+    let mut aov = PixelAov {
+        depth: f32::INFINITY,
+        normal: Vec3::zero(),
+        albedo: Color::zero(),
+        material_id: 0,
+        object_id: 0,
+        alpha: 0.0,
+    };
+    sampler.start_pixel_sample(x, y, 0);
+    if let Some(ray) = camera.primary_ray(x as f64 + 0.5, y as f64 + 0.5, sampler) {
+        if let Some(hit) = camera.first_hit(&ray, sampler) {
+            aov.depth = Vec3::dot(&(hit.point - camera.center), &camera.direction) as f32;
+            aov.material_id = hit.material_id;
+            aov.object_id = hit.object_id;
+        }
+    }
+    for k in 0..AOV_SAMPLES {
+        sampler.start_pixel_sample(x, y, k);
+        let (dx, dy) = sampler.next_2d();
+        let ray = match camera.primary_ray(x as f64 + dx, y as f64 + dy, sampler) {
+            Some(ray) => ray,
+            None => continue,
+        };
+        if let Some(hit) = camera.first_hit(&ray, sampler) {
+            aov.normal += hit.normal / AOV_SAMPLES as f64;
+            aov.albedo += hit.material.albedo(&hit) / AOV_SAMPLES as f64;
+            aov.alpha += 1.0 / AOV_SAMPLES as f32;
+        }
+    }
+    aov
+}
+
+fn aov_tile(camera: &Camera, tile: Tile, sampler: &dyn Sampler) -> Vec<PixelAov> {
+    let mut sampler = sampler.clone_box();
+    let mut pixels = Vec::with_capacity((tile.width * tile.height) as usize);
+    for j in tile.y..tile.y + tile.height {
+        for i in tile.x..tile.x + tile.width {
+            pixels.push(sample_pixel_aov(camera, i, j, sampler.as_mut()));
+        }
+    }
+    pixels
+}
+
+pub fn render_aovs(camera: &Arc<Camera>, settings: &RenderSettings) -> Aovs {
+    let (width, height) = (camera.image_width, camera.image_height);
+    let n = (width * height) as usize;
+    let mut aovs = Aovs {
+        width,
+        height,
+        depth: vec![f32::INFINITY; n],
+        normal: vec![Vec3::zero(); n],
+        albedo: vec![Color::zero(); n],
+        material_id: vec![0; n],
+        object_id: vec![0; n],
+        alpha: vec![0.0; n],
+    };
+    let sampler: Arc<dyn Sampler> = Arc::from(settings.sampler.build(settings.seed, AOV_SAMPLES));
+    let tiles = spiral_tiles(width, height, settings.tile_size);
+    render_tiles(
+        camera,
+        tiles,
+        settings.n_threads,
+        &settings.cancel,
+        move |camera, tile| aov_tile(camera, tile, sampler.as_ref()),
+        |tile, pixels| {
+            for (k, pixel) in pixels.into_iter().enumerate() {
+                let (i, j) = (tile.x + k as u32 % tile.width, tile.y + k as u32 / tile.width);
+                let index = (j * width + i) as usize;
+                aovs.depth[index] = pixel.depth;
+                aovs.normal[index] = pixel.normal;
+                aovs.albedo[index] = pixel.albedo;
+                aovs.material_id[index] = pixel.material_id;
+                aovs.object_id[index] = pixel.object_id;
+                aovs.alpha[index] = pixel.alpha;
+            }
+        },
+    );
+
+    aovs
+}
+
+// distinct colors for neighbouring IDs, black for 0
+fn id_color(id: u32) -> Rgb<u8> {
+    if id == 0 {
+        return Rgb([0, 0, 0]);
+    }
+    let h = id.wrapping_mul(0x9e3779b1) ^ (id.wrapping_mul(0x85ebca6b) >> 13);
+    Rgb([(h >> 24) as u8 | 0x40, (h >> 16) as u8 | 0x40, (h >> 8) as u8 | 0x40])
+}
+
+impl Aovs {
+    fn image(&self, f: impl Fn(usize) -> Rgb<u8>) -> RgbImage {
+        ImageBuffer::from_fn(self.width, self.height, |i, j| f((j * self.width + i) as usize))
+    }
+
+    // A path ending in .exr gets one file with the beauty image as RGBA and a layer per AOV.
+    // Anything else is a prefix for one PNG per AOV, e.g. output/aov_depth.png.
+    pub fn save(&self, path: &str, beauty: &HdrImage) -> Result<(), String> {
+        if path.to_ascii_lowercase().ends_with(".exr") {
+            return self.save_exr(path, beauty);
+        }
+        // near is bright, nothing hit is black
+        let max_depth = self.depth.iter().cloned().filter(|d| d.is_finite()).fold(1e-6, f32::max);
+        let nearness = |k: usize| if self.depth[k].is_finite() { 1.0 - (self.depth[k] / max_depth) as f64 } else { 0.0 };
+        let gray = |v: f64| Rgb([tonemap::quantize(v); 3]);
+        let images = [
+            ("depth", self.image(|k| gray(nearness(k)))),
+            ("normal", self.image(|k| Camera::color2rgb(self.normal[k] * 0.5 + Vec3::ones() * 0.5))),
+            ("albedo", self.image(|k| Camera::color2rgb(Camera::linear_to_gamma(self.albedo[k])))),
+            ("material", self.image(|k| id_color(self.material_id[k]))),
+            ("object", self.image(|k| id_color(self.object_id[k]))),
+            ("alpha", self.image(|k| gray(self.alpha[k] as f64))),
+        ];
+        for (name, image) in images.iter() {
+            let file = format!("{}_{}.png", path, name);
+            image.save(&file).map_err(|e| format!("failed to write {}: {}", file, e))?;
+        }
+        Ok(())
+    }
+
+    fn save_exr(&self, path: &str, beauty: &HdrImage) -> Result<(), String> {
+        let vector = |data: &[Vec3], c: usize| -> Vec<f32> { data.iter().map(|v| v.get_axis(c) as f32).collect() };
+        let ids = |data: &[u32]| -> Vec<f32> { data.iter().map(|&id| id as f32).collect() };
+        let planes = vec![
+            ("R", beauty.channel(0)),
+            ("G", beauty.channel(1)),
+            ("B", beauty.channel(2)),
+            ("A", self.alpha.clone()),
+            ("depth.Z", self.depth.clone()),
+            ("normal.X", vector(&self.normal, 0)),
+            ("normal.Y", vector(&self.normal, 1)),
+            ("normal.Z", vector(&self.normal, 2)),
+            ("albedo.R", vector(&self.albedo, 0)),
+            ("albedo.G", vector(&self.albedo, 1)),
+            ("albedo.B", vector(&self.albedo, 2)),
+            ("material.id", ids(&self.material_id)),
+            ("object.id", ids(&self.object_id)),
+        ];
+        let channels: Vec<(&str, &[f32])> = planes.iter().map(|(name, data)| (*name, &data[..])).collect();
+        std::fs::write(path, encode_exr(self.width, self.height, &channels))
+            .map_err(|e| format!("failed to write {}: {}", path, e))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::CameraBuilder;
+    use crate::hittable::sphere::Sphere;
+    use crate::material::diffusive::Diffusive;
+    use crate::material::metal::Metal;
+    use crate::util::vec3::Point3;
+    use crate::world::World;
+
+    #[test]
+    fn test_first_hit_buffers() {
+        let matte = Sphere::new(Point3::new(0.0, 0.0, -2.0), 0.5, Diffusive::new(Color::new(0.2, 0.4, 0.6)));
+        let shiny = Sphere::new(Point3::new(1.0, 0.0, -2.0), 0.3, Metal::new(Color::ones(), 0.0));
+        let world = World { hittables: vec![Box::new(shiny), Box::new(matte)], material_ids: vec![], fog: None };
+        let camera = CameraBuilder::new().image_width(9).aspect_ratio(1.0).build(world).unwrap();
+        let aovs = render_aovs(&Arc::new(camera), &RenderSettings { tile_size: 4, ..RenderSettings::default() });
+
+        let center = (4 * 9 + 4) as usize;
+        assert_eq!(aovs.object_id[center], 2);
+        assert!((aovs.depth[center] - 1.5).abs() < 0.05);
+        assert_eq!(aovs.alpha[center], 1.0);
+        assert!((aovs.albedo[center] - Color::new(0.2, 0.4, 0.6)).length() < 1e-9);
+        assert!(aovs.normal[center].z > 0.9);
+        assert_eq!(aovs.material_id[center], 2);
+
+        assert_eq!((aovs.object_id[0], aovs.material_id[0], aovs.alpha[0]), (0, 0, 0.0));
+        assert!(aovs.depth[0].is_infinite());
+    }
+
+    #[test]
+    fn test_shared_material_ids() {
+        // two copies of one material on the left and right, a different one in the middle
+        let gold = Metal::new(Color::new(0.8, 0.6, 0.2), 0.0);
+        let mut world = World { hittables: vec![], material_ids: vec![], fog: None };
+        world.add_hittable_with_material(Sphere::new(Point3::new(-1.0, 0.0, -2.0), 0.3, gold), 7);
+        world.add_hittable(Sphere::new(Point3::new(0.0, 0.0, -2.0), 0.3, gold));
+        world.add_hittable_with_material(Sphere::new(Point3::new(1.0, 0.0, -2.0), 0.3, gold), 7);
+        let camera = CameraBuilder::new().image_width(9).aspect_ratio(1.0).build(world).unwrap();
+        let aovs = render_aovs(&Arc::new(camera), &RenderSettings::default());
+
+        let (left, center, right) = ((4 * 9 + 1) as usize, (4 * 9 + 4) as usize, (4 * 9 + 7) as usize);
+        assert_eq!((aovs.object_id[left], aovs.object_id[center], aovs.object_id[right]), (1, 2, 3));
+        assert_eq!((aovs.material_id[left], aovs.material_id[center], aovs.material_id[right]), (7, 8, 7));
+    }
+}
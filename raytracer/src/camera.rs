@@ -1,15 +1,20 @@
 
 use image::{ImageBuffer, Rgb, RgbImage};
 use indicatif::ProgressBar;
-use rand::Rng;
+use crate::util::sampler::{Independent, Sampler, DEFAULT_SEED};
 
 use crate::hittable::{Hittable, HitRecord};
 use crate::util::const_value;
+use crate::util::spectrum;
 use crate::util::ray::Ray;
 use crate::util::interval::Interval;
 use crate::util::vec3::{Color, Point3, Vec3};
-use crate::world::World;
+use crate::world::{Fog, World};
 use crate::util::bvh::BVHNode;
+use crate::projection::{Projection, ProjectionKind};
+use crate::projection::perspective::Perspective;
+use crate::stereo::{Eye, StereoRig};
+use crate::tonemap;
 
 pub struct Camera {
     // user specified parameters
@@ -23,10 +28,23 @@ pub struct Camera {
     pub pixel0_loc: Point3,
     pub du: Vec3, // unit pixel vector of u axis
     pub dv: Vec3, // unit pixel vector of v axis
+    pub u_unit: Vec3,
+    pub v_unit: Vec3,
+    pub direction: Vec3, // unit viewing direction
+    pub aperture: f64, // diameter of the lens, 0 for a pinhole
+    pub focus_dist: f64, // distance from the lens to the plane in focus
+    pub shutter_open: f64,
+    pub shutter_close: f64,
     pub bvh_tree: Option<BVHNode>,
+    pub spectral: bool, // trace a single wavelength per path instead of rgb
+    pub fog: Option<Fog>,
+    pub projection: Box<dyn Projection>,
+    pub stereo: Option<(StereoRig, Eye)>, // eye to render when rendering stereo pairs
 }
 
 impl Camera {
+    // Low level constructor, u points to the right of the image and has to be
+    // perpendicular to the viewing direction. See CameraBuilder for a friendlier option.
     pub fn new(
         center: Point3,
         look_to: Vec3,
@@ -39,8 +57,40 @@ impl Camera {
         background_color: Color,
     ) -> Self {
         let image_height = (image_width as f64 / aspect_ratio) as u32;
-        let viewport_height = viewport_width / image_width as f64 * image_height as f64;
-        let pixel_length = viewport_width / image_width as f64;
+        let fog = world.fog;
+        let bvh_tree = Some(BVHNode::new_from_world(world));
+
+        let mut camera = Self {
+            center,
+            background_color,
+            image_width,
+            image_height,
+            viewport_height: 0.0,
+            pixel_length: 0.0,
+            pixel0_loc: center,
+            du: Vec3::zero(),
+            dv: Vec3::zero(),
+            u_unit: Vec3::zero(),
+            v_unit: Vec3::zero(),
+            direction: Vec3::zero(),
+            aperture: 0.0,
+            focus_dist: focal_length,
+            shutter_open: 0.0,
+            shutter_close: 0.0,
+            bvh_tree,
+            spectral: false,
+            fog,
+            projection: Box::new(Perspective),
+            stereo: None,
+        };
+        camera.set_pixel_grid(center, look_to, focal_length, viewport_width, u);
+        camera
+    }
+
+    // place the pixel grid focal_length in front of center, keeping the image size
+    fn set_pixel_grid(&mut self, center: Point3, look_to: Vec3, focal_length: f64, viewport_width: f64, u: Vec3) {
+        let viewport_height = viewport_width / self.image_width as f64 * self.image_height as f64;
+        let pixel_length = viewport_width / self.image_width as f64;
         let direction = look_to - center;
 
         let u_unit = u.unit();
@@ -49,92 +99,197 @@ impl Camera {
         let left_corner: Point3 = center + direction.unit() * focal_length
             - u_unit * (0.5 * viewport_width)
             - v_unit * (0.5 * viewport_height);
-        let pixel0_loc = left_corner + u_unit * 0.5 * pixel_length + v_unit * 0.5 * pixel_length;
 
-        let du = u_unit * pixel_length;
-        let dv = v_unit * pixel_length;
-        let bvh_tree = Some(BVHNode::new_from_world(world));
+        self.center = center;
+        self.viewport_height = viewport_height;
+        self.pixel_length = pixel_length;
+        self.pixel0_loc = left_corner + u_unit * 0.5 * pixel_length + v_unit * 0.5 * pixel_length;
+        self.du = u_unit * pixel_length;
+        self.dv = v_unit * pixel_length;
+        self.u_unit = u_unit;
+        self.v_unit = v_unit;
+        self.direction = direction.unit();
+        self.focus_dist = focal_length;
+    }
 
-        Self {
-            center,
-            background_color,
-            image_width,
-            image_height,
-            viewport_height,
-            pixel_length,
-            pixel0_loc,
-            du,
-            dv,
-            bvh_tree,
-        }
+    // Move and re-aim an existing camera without rebuilding the bvh, e.g. between
+    // frames of an animation. The aperture is kept and the focus follows lookat.
+    pub fn set_view(&mut self, lookfrom: Point3, lookat: Point3, vup: Vec3, vfov: f64) -> Result<(), String> {
+        let aspect_ratio = self.image_width as f64 / self.image_height as f64;
+        let right = CameraBuilder::new()
+            .lookfrom(lookfrom)
+            .lookat(lookat)
+            .vup(vup)
+            .vfov(vfov)
+            .aspect_ratio(aspect_ratio)
+            .image_width(self.image_width)
+            .validate()?;
+        let viewport_height = 2.0 * (vfov.to_radians() / 2.0).tan();
+        self.set_pixel_grid(lookfrom, lookat, 1.0, viewport_height * aspect_ratio, right);
+        self.set_depth_of_field(self.aperture, (lookat - lookfrom).length());
+        Ok(())
     }
 
+    // display values in [0, 1] to 8 bit, anything outside is clipped
     pub fn color2rgb(color: Color) -> Rgb<u8> {
-        Rgb([
-            (color.x * 255.999) as u8,
-            (color.y * 255.999) as u8,
-            (color.z * 255.999) as u8,
-        ])
+        Rgb([tonemap::quantize(color.x), tonemap::quantize(color.y), tonemap::quantize(color.z)])
     }
 
     pub fn linear_to_gamma(color: Color) -> Color {
-        Color::new(color.x.sqrt(), color.y.sqrt(), color.z.sqrt())
+        Color::new(tonemap::srgb_oetf(color.x), tonemap::srgb_oetf(color.y), tonemap::srgb_oetf(color.z))
+    }
+
+    // Turn the pinhole into a thin lens. The pixel grid is moved onto the focus plane,
+    // so everything at focus_dist stays sharp while the rest gets blurred.
+    pub fn set_depth_of_field(&mut self, aperture: f64, focus_dist: f64) {
+        let scale = focus_dist / self.focus_dist;
+        self.pixel0_loc = self.center + (self.pixel0_loc - self.center) * scale;
+        self.du *= scale;
+        self.dv *= scale;
+        self.aperture = aperture;
+        self.focus_dist = focus_dist;
     }
 
-    pub fn cast_ray(&self, pixel_loc: &Point3) -> Ray {
-        let ray_direction = (pixel_loc.clone() - self.center).unit();
-        return Ray::new(self.center, ray_direction);
+    // keep the current aperture and focus on the plane through the given point
+    pub fn focus_on(&mut self, point: Point3) -> Result<(), String> {
+        let focus_dist = Vec3::dot(&(point - self.center), &self.direction);
+        if focus_dist <= 0.0 {
+            return Err("cannot focus on a point behind the camera".to_string());
+        }
+        self.set_depth_of_field(self.aperture, focus_dist);
+        Ok(())
+    }
+
+    pub fn cast_ray(&self, pixel_loc: &Point3, sampler: &mut dyn Sampler) -> Ray {
+        let mut origin = self.center;
+        if self.aperture > 0.0 {
+            let lens = Vec3::random_in_unit_disk(sampler) * (0.5 * self.aperture);
+            origin += self.u_unit * lens.x + self.v_unit * lens.y;
+        }
+        let ray_direction = (*pixel_loc - origin).unit();
+        Ray::new(origin, ray_direction)
+    }
+
+    // closest surface or fog scattering event along the ray
+    pub fn hit_scene(&self, ray: &Ray, sampler: &mut dyn Sampler) -> Option<HitRecord> {
+        let rot = Interval::new(0.001, const_value::BACKGROUND_T);
+        let mut _hit_record: Option<HitRecord> = None;
+
+        if let Some(bvh_tree) = &self.bvh_tree {
+            _hit_record = bvh_tree.hit(ray, &rot, sampler);
+        }
+
+        if let Some(fog) = &self.fog {
+            _hit_record = fog.hit(ray, &rot, _hit_record, sampler);
+        }
+        _hit_record
     }
 
-    pub fn get_color(&self, ray: Ray, bounce_time: u32) -> Color {
+    pub fn get_color(&self, ray: Ray, bounce_time: u32, sampler: &mut dyn Sampler) -> Color {
         let a = 0.5 * (ray.dir.y + 1.0);
         let bounce_time = bounce_time + 1;
-        let mut _hit_record: Option<HitRecord> = None;
         if bounce_time > const_value::MAX_BOUNCING_TIMES {
             return Color::new(0.0, 0.0, 0.0);
         }
 
-        let rot = Interval::new(0.001, const_value::BACKGROUND_T);
+        match self.hit_scene(&ray, sampler) {
+            None => self.background_color,
+            Some(hit_record) => {
+                let emitted = hit_record.material.emitted(&hit_record);
+                if hit_record.material.is_light() {
+                    return emitted;
+                }
+                let (mut scattered_ray, attenuation) = hit_record.material.scatter_with_attenuation(&ray, &hit_record, sampler);
+                if attenuation.near_zero() {
+                    return emitted;
+                }
+                scattered_ray.inherit(&ray);
+                emitted + attenuation * self.get_color(scattered_ray, bounce_time, sampler)
+            }
+        }
+    }
 
-        if let Some(bvh_tree) = &self.bvh_tree{
-            _hit_record = bvh_tree.hit(&ray, &rot);   
+    // radiance carried by a ray at its wavelength, materials and lights are upsampled from rgb
+    pub fn get_radiance_spectral(&self, ray: Ray, bounce_time: u32, sampler: &mut dyn Sampler) -> f64 {
+        let lambda = ray.wavelength.expect("spectral ray without wavelength");
+        let bounce_time = bounce_time + 1;
+        if bounce_time > const_value::MAX_BOUNCING_TIMES {
+            return 0.0;
         }
 
-        match _hit_record {
-            None => self.background_color,
+        match self.hit_scene(&ray, sampler) {
+            None => spectrum::rgb_to_spectrum(self.background_color, lambda),
             Some(hit_record) => {
+                let emitted = spectrum::rgb_to_spectrum(hit_record.material.emitted(&hit_record), lambda);
                 if hit_record.material.is_light() {
-                    return hit_record.material.attenuation();
+                    return emitted;
                 }
-                let scattered_ray = hit_record.material.scatter(&ray, &hit_record);
-                hit_record.material.attenuation() * self.get_color(scattered_ray, bounce_time)
+                let (mut scattered_ray, attenuation) = hit_record.material.scatter_with_attenuation(&ray, &hit_record, sampler);
+                if attenuation.near_zero() {
+                    return emitted;
+                }
+                scattered_ray.inherit(&ray);
+                emitted + spectrum::rgb_to_spectrum(attenuation, lambda) * self.get_radiance_spectral(scattered_ray, bounce_time, sampler)
             }
         }
     }
 
-    pub fn get_pixel_color(&self, pixel_loc: Point3) -> Color {
-        let mut rng = rand::thread_rng();
+    // radiance of one random sample inside pixel (i, j)
+    pub fn sample_pixel(&self, i: u32, j: u32, sampler: &mut dyn Sampler) -> Color {
+        let (dx, dy) = sampler.next_2d();
+        self.sample(i as f64 + dx, j as f64 + dy, sampler)
+    }
+
+    // ray through the continuous image position (x, y) at a random time during the exposure,
+    // None where the projection has no ray
+    pub fn primary_ray(&self, x: f64, y: f64, sampler: &mut dyn Sampler) -> Option<Ray> {
+        let mut ray = self.projection.cast_ray(self, x, y, sampler)?;
+        if let Some((rig, eye)) = &self.stereo {
+            ray = rig.offset_ray(*eye, self, ray);
+        }
+        ray.time = self.shutter_open + (self.shutter_close - self.shutter_open) * sampler.next_f64();
+        Some(ray)
+    }
+
+    // closest surface along the ray, ignoring the fog
+    pub fn first_hit(&self, ray: &Ray, sampler: &mut dyn Sampler) -> Option<HitRecord> {
+        let rot = Interval::new(0.001, const_value::BACKGROUND_T);
+        self.bvh_tree.as_ref()?.hit(ray, &rot, sampler)
+    }
+
+    // radiance seen through the continuous image position (x, y), black where the projection has no ray
+    pub fn sample(&self, x: f64, y: f64, sampler: &mut dyn Sampler) -> Color {
+        let mut ray = match self.primary_ray(x, y, sampler) {
+            Some(ray) => ray,
+            None => return Color::zero(),
+        };
+        let bounce_times = 0;
+        if self.spectral {
+            let lambda = spectrum::sample_wavelength(sampler.next_f64());
+            ray.wavelength = Some(lambda);
+            spectrum::wavelength_to_rgb(lambda) * self.get_radiance_spectral(ray, bounce_times, sampler)
+        } else {
+            self.get_color(ray, bounce_times, sampler)
+        }
+    }
+
+    pub fn get_pixel_color(&self, i: u32, j: u32) -> Color {
         let mut color: Color = Color::new(0.0, 0.0, 0.0);
-        for _ in 0..const_value::RAY_PER_PIXEL {
-            let u_offset: f64 = rng.gen::<f64>() - 0.5;
-            let v_offset: f64 = rng.gen::<f64>() - 0.5; 
-            let pixel_loc_new = pixel_loc + self.du * u_offset + self.dv * v_offset;
-            let ray = self.cast_ray(&pixel_loc_new);
-            let bounce_times = 0;
-            color += self.get_color(ray, bounce_times);
+        let mut sampler = Independent::new(DEFAULT_SEED);
+        for k in 0..const_value::RAY_PER_PIXEL {
+            sampler.start_pixel_sample(i, j, k as u32);
+            color += self.sample_pixel(i, j, &mut sampler);
         }
         color / const_value::RAY_PER_PIXEL as f64
     }
 
     pub fn render(&self) -> RgbImage {
-        let mut _pixel = Point3::new(0.0, 0.0, 0.0);
         let mut result: RgbImage = ImageBuffer::new(self.image_width, self.image_height);
         let bar = ProgressBar::new((self.image_height * self.image_width) as u64);
 
         for j in 0..self.image_height {
             for i in 0..self.image_width {
-                _pixel = self.pixel0_loc + self.du * i as f64 + self.dv * j as f64;
-                let color = self.get_pixel_color(_pixel);
+                let color = self.get_pixel_color(i, j);
                 let color = Self::linear_to_gamma(color);
                 result.put_pixel(
                     i,
@@ -153,3 +308,260 @@ impl Camera {
     }
 
 }
+
+// Camera description in terms of a viewpoint, a target and a vertical field of view.
+// The orthonormal basis is derived from vup, which only has to be roughly upwards.
+#[derive(Clone, Copy, Debug)]
+pub struct CameraBuilder {
+    lookfrom: Point3,
+    lookat: Point3,
+    vup: Vec3,
+    vfov: f64, // vertical field of view in degrees
+    aspect_ratio: f64,
+    image_width: u32,
+    background_color: Color,
+    aperture: f64,
+    focus_dist: Option<f64>, // defaults to the distance between lookfrom and lookat
+    shutter_open: f64,
+    shutter_close: f64,
+    spectral: bool,
+    projection: ProjectionKind,
+}
+
+impl Default for CameraBuilder {
+    fn default() -> Self {
+        Self {
+            lookfrom: Point3::new(0.0, 0.0, 0.0),
+            lookat: Point3::new(0.0, 0.0, -1.0),
+            vup: Vec3::new(0.0, 1.0, 0.0),
+            vfov: 90.0,
+            aspect_ratio: 16.0 / 9.0,
+            image_width: 400,
+            background_color: Color::new(0.0, 0.0, 0.0),
+            aperture: 0.0,
+            focus_dist: None,
+            shutter_open: 0.0,
+            shutter_close: 0.0,
+            spectral: false,
+            projection: ProjectionKind::Perspective,
+        }
+    }
+}
+
+impl CameraBuilder {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn lookfrom(mut self, lookfrom: Point3) -> Self {
+        self.lookfrom = lookfrom;
+        self
+    }
+
+    pub fn lookat(mut self, lookat: Point3) -> Self {
+        self.lookat = lookat;
+        self
+    }
+
+    pub fn vup(mut self, vup: Vec3) -> Self {
+        self.vup = vup;
+        self
+    }
+
+    pub fn vfov(mut self, vfov: f64) -> Self {
+        self.vfov = vfov;
+        self
+    }
+
+    pub fn aspect_ratio(mut self, aspect_ratio: f64) -> Self {
+        self.aspect_ratio = aspect_ratio;
+        self
+    }
+
+    pub fn image_width(mut self, image_width: u32) -> Self {
+        self.image_width = image_width;
+        self
+    }
+
+    pub fn background_color(mut self, background_color: Color) -> Self {
+        self.background_color = background_color;
+        self
+    }
+
+    pub fn aperture(mut self, aperture: f64) -> Self {
+        self.aperture = aperture;
+        self
+    }
+
+    pub fn focus_dist(mut self, focus_dist: f64) -> Self {
+        self.focus_dist = Some(focus_dist);
+        self
+    }
+
+    // rays are spread uniformly over the time the shutter is open
+    pub fn shutter(mut self, shutter_open: f64, shutter_close: f64) -> Self {
+        self.shutter_open = shutter_open;
+        self.shutter_close = shutter_close;
+        self
+    }
+
+    // trace one wavelength per path, needed for dispersion in glass
+    pub fn spectral(mut self, spectral: bool) -> Self {
+        self.spectral = spectral;
+        self
+    }
+
+    // vfov and the depth of field only apply to the perspective projection
+    pub fn projection(mut self, projection: ProjectionKind) -> Self {
+        self.projection = projection;
+        self
+    }
+
+    pub fn projection_kind(&self) -> ProjectionKind {
+        self.projection
+    }
+
+    // check the parameters and return the unit vector pointing to the right of the image
+    fn validate(&self) -> Result<Vec3, String> {
+        let direction = self.lookat - self.lookfrom;
+        if direction.near_zero() {
+            return Err("lookfrom and lookat must not coincide".to_string());
+        }
+        if self.vup.near_zero() {
+            return Err("vup must not be zero".to_string());
+        }
+        let right = Vec3::cross(&direction.unit(), &self.vup.unit());
+        if right.length() < 1e-6 {
+            return Err("vup must not be parallel to the viewing direction".to_string());
+        }
+        if !(self.vfov > 0.0 && self.vfov < 180.0) {
+            return Err(format!("vertical field of view must be in (0, 180) degrees, got {}", self.vfov));
+        }
+        if !(self.aspect_ratio > 0.0 && self.aspect_ratio.is_finite()) {
+            return Err(format!("aspect ratio must be positive, got {}", self.aspect_ratio));
+        }
+        if self.image_width == 0 || (self.image_width as f64 / self.aspect_ratio) < 1.0 {
+            return Err("image must be at least one pixel wide and high".to_string());
+        }
+        if self.aperture < 0.0 {
+            return Err(format!("aperture must not be negative, got {}", self.aperture));
+        }
+        self.projection.validate()?;
+        if self.shutter_close < self.shutter_open {
+            return Err("the shutter must not close before it opens".to_string());
+        }
+        if let Some(focus_dist) = self.focus_dist {
+            if focus_dist <= 0.0 {
+                return Err(format!("focus distance must be positive, got {}", focus_dist));
+            }
+        }
+        Ok(right.unit())
+    }
+
+    pub fn build(self, world: World) -> Result<Camera, String> {
+        let right = self.validate()?;
+        let focal_length = 1.0;
+        let viewport_height = 2.0 * (self.vfov.to_radians() / 2.0).tan() * focal_length;
+        let viewport_width = viewport_height * self.aspect_ratio;
+
+        let mut camera = Camera::new(
+            self.lookfrom,
+            self.lookat,
+            focal_length,
+            self.aspect_ratio,
+            self.image_width,
+            viewport_width,
+            right,
+            world,
+            self.background_color,
+        );
+        let focus_dist = self
+            .focus_dist
+            .unwrap_or_else(|| (self.lookat - self.lookfrom).length());
+        camera.set_depth_of_field(self.aperture, focus_dist);
+        camera.shutter_open = self.shutter_open;
+        camera.shutter_close = self.shutter_close;
+        camera.spectral = self.spectral;
+        camera.projection = self.projection.build();
+        Ok(camera)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hittable::sphere::Sphere;
+    use crate::material::diffusive::Diffusive;
+
+    #[test]
+    fn test_builder_basis() {
+        let builder = CameraBuilder::new()
+            .lookfrom(Point3::new(-3.0, 0.0, 1.0))
+            .lookat(Point3::new(0.0, 0.0, 0.0))
+            .vup(Vec3::new(0.0, 0.0, 1.0));
+        let right = builder.validate().unwrap();
+        assert!((right - Vec3::new(0.0, -1.0, 0.0)).length() < 1e-12);
+    }
+
+    #[test]
+    fn test_builder_degenerate() {
+        let builder = CameraBuilder::new();
+        assert!(builder.lookat(Point3::new(0.0, 0.0, 0.0)).validate().is_err());
+        assert!(builder.vup(Vec3::new(0.0, 0.0, 2.0)).validate().is_err());
+        assert!(builder.vup(Vec3::new(0.0, 0.0, 0.0)).validate().is_err());
+        assert!(builder.vfov(180.0).validate().is_err());
+        assert!(builder.aspect_ratio(0.0).validate().is_err());
+        assert!(builder.image_width(0).validate().is_err());
+        assert!(builder.aperture(-1.0).validate().is_err());
+        assert!(builder.focus_dist(0.0).validate().is_err());
+        assert!(builder.shutter(1.0, 0.0).validate().is_err());
+        assert!(builder.validate().is_ok());
+    }
+
+    fn camera(aperture: f64) -> Camera {
+        let sphere = Sphere::new(Point3::new(0.0, 0.0, -5.0), 1.0, Diffusive::new(Color::ones()));
+        let world = World { hittables: vec![Box::new(sphere)], material_ids: vec![], fog: None };
+        CameraBuilder::new().image_width(20).aperture(aperture).focus_dist(3.0).build(world).unwrap()
+    }
+
+    #[test]
+    fn test_pinhole() {
+        let camera = camera(0.0);
+        let pixel = camera.pixel0_loc + camera.du * 7.0 + camera.dv * 4.0;
+        let mut sampler = Independent::new(1);
+        let first = camera.cast_ray(&pixel, &mut sampler);
+        for _ in 0..100 {
+            let ray = camera.cast_ray(&pixel, &mut sampler);
+            assert_eq!(ray.ori, camera.center);
+            assert!((ray.dir - first.dir).length() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_rays_converge_at_focus_dist() {
+        let mut camera = camera(0.5);
+        let pixel = camera.pixel0_loc + camera.du * 7.0 + camera.dv * 4.0;
+        let mut sampler = Independent::new(2);
+        // where the ray crosses the plane focus_dist in front of the camera
+        let on_focus_plane = |camera: &Camera, ray: &Ray| {
+            let t = (camera.focus_dist - Vec3::dot(&(ray.ori - camera.center), &camera.direction)) / Vec3::dot(&ray.dir, &camera.direction);
+            ray.at(t)
+        };
+        let mut spread = 0.0;
+        for _ in 0..100 {
+            let ray = camera.cast_ray(&pixel, &mut sampler);
+            spread += (ray.ori - camera.center).length();
+            assert!((on_focus_plane(&camera, &ray) - pixel).length() < 1e-9);
+        }
+        // the lens is actually used
+        assert!(spread / 100.0 > 0.1);
+
+        camera.focus_on(Point3::new(0.0, 0.0, -5.0)).unwrap();
+        assert!((camera.focus_dist - 5.0).abs() < 1e-12);
+        let pixel = camera.pixel0_loc + camera.du * 7.0 + camera.dv * 4.0;
+        let ray = camera.cast_ray(&pixel, &mut sampler);
+        assert!((on_focus_plane(&camera, &ray) - pixel).length() < 1e-9);
+        assert!(camera.focus_on(Point3::new(0.0, 0.0, 1.0)).is_err());
+        assert!((camera.focus_dist - 5.0).abs() < 1e-12);
+    }
+}
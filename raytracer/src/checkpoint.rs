@@ -0,0 +1,272 @@
+// Snapshot of a progressive render that can be resumed later. The file is little endian:
+//   magic "RTCKPT06", width u32, height u32, samples per pass u32, completed samples u32,
+//   seed u64, sampler u8, sampler samples u32, filter u8, filter radius f64,
+//   adaptive u8 (+ min samples u32, target error f64), max radiance u8 (+ f64), estimator u8,
+// followed by sum rgb f64 x 3, n u32, mean f64, m2 f64, then splat sum rgb f64 x 3 and
+// splat weight f64 for every estimator batch, for every pixel row by row.
+// The random numbers of a sample only depend on the sampler, the seed and the sample index,
+// so together with the completed sample count they are all the generator state there is.
+// The sample count the sampler was built for is kept as well, since stratified sampling
+// depends on it and the render may be resumed with a different --spp.
+
+use std::fs;
+
+use crate::film::{Estimator, Film, PixelStats, Splat};
+use crate::filter::{Filter, FilterKind};
+use crate::render::{Adaptive, RenderSettings};
+use crate::util::sampler::SamplerKind;
+use crate::util::vec3::Color;
+
+const MAGIC: &[u8; 8] = b"RTCKPT06";
+// sum, n, mean and m2 of a pixel, and sum and weight of one of its batch splats
+const PIXEL_BYTES: usize = 3 * 8 + 4 + 8 + 8;
+const SPLAT_BYTES: usize = 4 * 8;
+
+pub struct Checkpoint {
+    pub film: Film,
+    pub done: u32, // samples every active pixel has got so far
+    pub samples_per_pass: u32,
+    pub seed: u64,
+    pub sampler: SamplerKind,
+    pub sampler_samples: u32,
+    pub filter: Filter,
+    pub adaptive: Option<Adaptive>,
+    pub max_radiance: Option<f64>,
+}
+
+impl Checkpoint {
+    pub fn new(film: &Film, done: u32, settings: &RenderSettings) -> Self {
+        Self {
+            film: film.clone(),
+            done,
+            samples_per_pass: settings.samples_per_pass,
+            seed: settings.seed,
+            sampler: settings.sampler,
+            sampler_samples: settings.sampler_samples.unwrap_or(settings.samples_per_pixel),
+            filter: settings.filter,
+            adaptive: settings.adaptive,
+            max_radiance: settings.max_radiance,
+        }
+    }
+
+    // keep sampling the way the checkpoint was rendered, only the sample count may change
+    pub fn apply_settings(&self, settings: &mut RenderSettings) {
+        settings.samples_per_pass = self.samples_per_pass;
+        settings.seed = self.seed;
+        settings.sampler = self.sampler;
+        settings.sampler_samples = Some(self.sampler_samples);
+        settings.filter = self.filter;
+        settings.adaptive = self.adaptive;
+        settings.max_radiance = self.max_radiance;
+        settings.estimator = self.film.estimator();
+    }
+
+    pub fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = MAGIC.to_vec();
+        for value in &[self.film.width, self.film.height, self.samples_per_pass, self.done] {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        bytes.extend_from_slice(&self.seed.to_le_bytes());
+        bytes.push(SamplerKind::ALL.iter().position(|&kind| kind == self.sampler).unwrap() as u8);
+        bytes.extend_from_slice(&self.sampler_samples.to_le_bytes());
+        bytes.push(FilterKind::ALL.iter().position(|&kind| kind == self.filter.kind).unwrap() as u8);
+        bytes.extend_from_slice(&self.filter.radius.to_le_bytes());
+        match self.adaptive {
+            Some(adaptive) => {
+                bytes.push(1);
+                bytes.extend_from_slice(&adaptive.min_samples.to_le_bytes());
+                bytes.extend_from_slice(&adaptive.target_error.to_le_bytes());
+            }
+            None => bytes.push(0),
+        }
+        match self.max_radiance {
+            Some(max_radiance) => {
+                bytes.push(1);
+                bytes.extend_from_slice(&max_radiance.to_le_bytes());
+            }
+            None => bytes.push(0),
+        }
+        let estimator = self.film.estimator();
+        bytes.push(Estimator::ALL.iter().position(|&e| e == estimator).unwrap() as u8);
+        let batches = estimator.batches() as usize;
+        for (pixel, splats) in self.film.pixels().iter().zip(self.film.splats().chunks(batches)) {
+            let (sum, n, mean, m2) = pixel.parts();
+            for value in &[sum.x, sum.y, sum.z] {
+                bytes.extend_from_slice(&value.to_le_bytes());
+            }
+            bytes.extend_from_slice(&n.to_le_bytes());
+            for value in &[mean, m2] {
+                bytes.extend_from_slice(&value.to_le_bytes());
+            }
+            for splat in splats {
+                for value in &[splat.sum.x, splat.sum.y, splat.sum.z, splat.weight] {
+                    bytes.extend_from_slice(&value.to_le_bytes());
+                }
+            }
+        }
+        bytes
+    }
+
+    pub fn from_bytes(bytes: &[u8]) -> Result<Self, String> {
+        if !bytes.starts_with(MAGIC) {
+            return Err("not a render checkpoint".to_string());
+        }
+        let mut reader = Reader { bytes: &bytes[MAGIC.len()..] };
+        let width = reader.u32()?;
+        let height = reader.u32()?;
+        let samples_per_pass = reader.u32()?;
+        if samples_per_pass == 0 {
+            return Err("checkpoint has no samples per pass".to_string());
+        }
+        let done = reader.u32()?;
+        let seed = reader.u64()?;
+        let sampler = *SamplerKind::ALL.get(reader.u8()? as usize).ok_or("unknown sampler")?;
+        let sampler_samples = reader.u32()?;
+        let filter_kind = *FilterKind::ALL.get(reader.u8()? as usize).ok_or("unknown filter")?;
+        let filter = Filter::new(filter_kind, reader.f64()?)?;
+        let adaptive = match reader.u8()? {
+            0 => None,
+            _ => Some(Adaptive {
+                min_samples: reader.u32()?,
+                target_error: reader.f64()?,
+            }),
+        };
+        let max_radiance = match reader.u8()? {
+            0 => None,
+            _ => Some(reader.f64()?),
+        };
+        let estimator = *Estimator::ALL.get(reader.u8()? as usize).ok_or("unknown estimator")?;
+        // check the size before allocating anything for a possibly corrupt header
+        let batches = estimator.batches() as usize;
+        let n_pixels = (width as usize).checked_mul(height as usize);
+        let expected = n_pixels.and_then(|n| n.checked_mul(PIXEL_BYTES + batches * SPLAT_BYTES));
+        match expected {
+            Some(expected) if expected > reader.bytes.len() => return Err("truncated checkpoint".to_string()),
+            Some(expected) if expected < reader.bytes.len() => return Err("trailing data after the last pixel".to_string()),
+            Some(_) => {}
+            None => return Err(format!("checkpoint of {}x{} pixels is too large", width, height)),
+        }
+        let n_pixels = width as usize * height as usize;
+        let mut pixels = Vec::with_capacity(n_pixels);
+        let mut splats = Vec::with_capacity(n_pixels * batches);
+        for _ in 0..n_pixels {
+            let sum = Color::new(reader.f64()?, reader.f64()?, reader.f64()?);
+            pixels.push(PixelStats::from_parts(sum, reader.u32()?, reader.f64()?, reader.f64()?));
+            for _ in 0..estimator.batches() {
+                let sum = Color::new(reader.f64()?, reader.f64()?, reader.f64()?);
+                splats.push(Splat { sum, weight: reader.f64()? });
+            }
+        }
+        Ok(Self {
+            film: Film::from_pixels(width, height, estimator, pixels, splats),
+            done,
+            samples_per_pass,
+            seed,
+            sampler,
+            sampler_samples,
+            filter,
+            adaptive,
+            max_radiance,
+        })
+    }
+
+    // written to a temporary file first, so a crash while saving keeps the old checkpoint
+    pub fn save(&self, path: &str) -> Result<(), String> {
+        let tmp_path = format!("{}.tmp", path);
+        fs::write(&tmp_path, self.to_bytes()).map_err(|e| format!("failed to write {}: {}", tmp_path, e))?;
+        fs::rename(&tmp_path, path).map_err(|e| format!("failed to write {}: {}", path, e))
+    }
+
+    pub fn load(path: &str) -> Result<Self, String> {
+        let bytes = fs::read(path).map_err(|e| format!("failed to read {}: {}", path, e))?;
+        Self::from_bytes(&bytes).map_err(|e| format!("{}: {}", path, e))
+    }
+}
+
+struct Reader<'a> {
+    bytes: &'a [u8],
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, n: usize) -> Result<&'a [u8], String> {
+        if self.bytes.len() < n {
+            return Err("truncated checkpoint".to_string());
+        }
+        let (head, tail) = self.bytes.split_at(n);
+        self.bytes = tail;
+        Ok(head)
+    }
+
+    fn u8(&mut self) -> Result<u8, String> {
+        Ok(self.take(1)?[0])
+    }
+
+    fn u32(&mut self) -> Result<u32, String> {
+        let b = self.take(4)?;
+        Ok(u32::from_le_bytes([b[0], b[1], b[2], b[3]]))
+    }
+
+    fn u64(&mut self) -> Result<u64, String> {
+        let b = self.take(8)?;
+        Ok(u64::from_le_bytes([b[0], b[1], b[2], b[3], b[4], b[5], b[6], b[7]]))
+    }
+
+    fn f64(&mut self) -> Result<f64, String> {
+        Ok(f64::from_bits(self.u64()?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_round_trip() {
+        let mut film = Film::with_estimator(3, 2, Estimator::MedianOfMeans);
+        film.add_sample(1, 1, Color::new(0.5, 1.0, 2.0));
+        film.add_sample(1, 1, Color::new(0.25, 0.0, 1.0));
+        let settings = RenderSettings {
+            samples_per_pixel: 64,
+            samples_per_pass: 8,
+            seed: 42,
+            sampler: SamplerKind::Sobol,
+            filter: Filter::new(FilterKind::Mitchell, 1.8).unwrap(),
+            adaptive: Some(Adaptive { min_samples: 16, target_error: 0.05 }),
+            max_radiance: Some(20.0),
+            ..RenderSettings::default()
+        };
+        let bytes = Checkpoint::new(&film, 2, &settings).to_bytes();
+        let checkpoint = Checkpoint::from_bytes(&bytes).unwrap();
+        assert_eq!((checkpoint.film.width, checkpoint.film.height, checkpoint.done), (3, 2, 2));
+        assert_eq!((checkpoint.samples_per_pass, checkpoint.seed), (8, 42));
+        assert_eq!((checkpoint.sampler, checkpoint.sampler_samples), (SamplerKind::Sobol, 64));
+        assert_eq!(checkpoint.filter, settings.filter);
+        assert_eq!(checkpoint.adaptive.unwrap().min_samples, 16);
+        assert_eq!((checkpoint.max_radiance, checkpoint.film.estimator()), (Some(20.0), Estimator::MedianOfMeans));
+        assert_eq!(checkpoint.film.samples(1, 1), 2);
+        assert_eq!(checkpoint.film.pixel(1, 1), film.pixel(1, 1));
+        assert_eq!(checkpoint.film.stats(1, 1).variance(), film.stats(1, 1).variance());
+        assert!(Checkpoint::from_bytes(&bytes[..bytes.len() - 1]).is_err());
+    }
+
+    #[test]
+    fn test_corrupt_header() {
+        let film = Film::new(3, 2);
+        let bytes = Checkpoint::new(&film, 0, &RenderSettings::default()).to_bytes();
+        let error = |patch: &dyn Fn(&mut Vec<u8>)| {
+            let mut bytes = bytes.clone();
+            patch(&mut bytes);
+            Checkpoint::from_bytes(&bytes).err().unwrap_or_default()
+        };
+        // width and height right after the magic, then samples per pass
+        let huge = |bytes: &mut Vec<u8>| bytes[8..16].copy_from_slice(&[0xff; 8]);
+        assert_eq!(error(&huge), "checkpoint of 4294967295x4294967295 pixels is too large");
+        let wide = |bytes: &mut Vec<u8>| bytes[8..12].copy_from_slice(&1000u32.to_le_bytes());
+        assert_eq!(error(&wide), "truncated checkpoint");
+        let narrow = |bytes: &mut Vec<u8>| bytes[8..12].copy_from_slice(&1u32.to_le_bytes());
+        assert_eq!(error(&narrow), "trailing data after the last pixel");
+        let no_passes = |bytes: &mut Vec<u8>| bytes[16..20].copy_from_slice(&0u32.to_le_bytes());
+        assert_eq!(error(&no_passes), "checkpoint has no samples per pass");
+        assert_eq!(error(&|_: &mut Vec<u8>| {}), "");
+    }
+}
@@ -0,0 +1,220 @@
+// Edge avoiding à-trous wavelet filter guided by the AOVs and the per pixel variance, the
+// spatial part of SVGF (Schied et al. 2017). Every iteration blurs with a 5x5 B3 spline whose
+// taps are spread twice as far as in the previous one, and each tap is weighted down where
+// normals, depth or luminance differ. The luminance threshold scales with the estimated noise,
+// so noisy pixels get smoothed a lot and converged ones are left alone.
+//
+// The color is divided by the albedo before filtering and multiplied back afterwards, so
+// textures stay sharp and only the lighting is smoothed.
+
+use crate::aov::Aovs;
+use crate::film::{luminance, Film};
+use crate::hdr::HdrImage;
+use crate::util::vec3::{Color, Vec3};
+
+// the taps of the last iteration are 2^15 pixels apart, far beyond any image
+pub const MAX_ITERATIONS: u32 = 16;
+
+const KERNEL: [f64; 5] = [1.0 / 16.0, 1.0 / 4.0, 3.0 / 8.0, 1.0 / 4.0, 1.0 / 16.0];
+
+#[derive(Clone, Copy, Debug)]
+pub struct Denoiser {
+    pub iterations: u32,
+    pub sigma_luminance: f64, // in standard deviations of the noise
+    pub sigma_normal: f64,    // exponent on the cosine between normals
+    pub sigma_depth: f64,     // relative depth difference per pixel of tap distance
+}
+
+impl Default for Denoiser {
+    fn default() -> Self {
+        Self { iterations: 5, sigma_luminance: 4.0, sigma_normal: 128.0, sigma_depth: 0.1 }
+    }
+}
+
+// per channel albedo to divide by, pixels without albedo (e.g. the background) are left as they are
+fn demodulation(albedo: Color) -> Color {
+    let channel = |a: f64| if a > 0.01 { a } else { 1.0 };
+    Color::new(channel(albedo.x), channel(albedo.y), channel(albedo.z))
+}
+
+impl Denoiser {
+    // how much pixel q may contribute to pixel p, sigma is the luminance threshold of p
+    fn edge_weight(&self, aovs: &Aovs, color: &[Color], p: usize, q: usize, step: u32, sigma: f64) -> f64 {
+        let (np, nq) = (aovs.normal[p], aovs.normal[q]);
+        let w_normal = match (np.near_zero(), nq.near_zero()) {
+            (true, true) => 1.0,
+            (false, false) => Vec3::dot(&np.unit(), &nq.unit()).max(0.0).powf(self.sigma_normal),
+            _ => 0.0,
+        };
+        let (zp, zq) = (aovs.depth[p] as f64, aovs.depth[q] as f64);
+        let w_depth = match (zp.is_finite(), zq.is_finite()) {
+            (true, true) => (-(zp - zq).abs() / (self.sigma_depth * zp.abs().max(1e-3) * step as f64)).exp(),
+            (false, false) => 1.0,
+            _ => 0.0,
+        };
+        let w_luminance = (-(luminance(color[p]) - luminance(color[q])).abs() / (sigma + 1e-6)).exp();
+        w_normal * w_depth * w_luminance
+    }
+
+    pub fn denoise(&self, film: &Film, aovs: &Aovs) -> HdrImage {
+        assert_eq!((film.width, film.height), (aovs.width, aovs.height), "AOVs do not match the film");
+        let (width, height) = (film.width as i64, film.height as i64);
+        let n = (width * height) as usize;
+        let mut demodulate = Vec::with_capacity(n);
+        let mut color = Vec::with_capacity(n);
+        let mut variance = Vec::with_capacity(n);
+        for j in 0..film.height {
+            for i in 0..film.width {
+                let d = demodulation(aovs.albedo[(j * film.width + i) as usize]);
+                let stats = film.stats(i, j);
+                // variance of the pixel mean, scaled like the color
+                let v = if stats.n > 0 { stats.variance() / stats.n as f64 } else { 0.0 };
+                let c = film.pixel(i, j);
+                color.push(Color::new(c.x / d.x, c.y / d.y, c.z / d.z));
+                variance.push(v / luminance(d).powi(2));
+                demodulate.push(d);
+            }
+        }
+
+        for iteration in 0..self.iterations.min(MAX_ITERATIONS) {
+            let step = 1 << iteration;
+            // every tap but the center one would fall outside the image
+            if step >= width.max(height) {
+                break;
+            }
+            let blurred = blur_3x3(&variance, width, height);
+            let mut next_color = vec![Color::zero(); n];
+            let mut next_variance = vec![0.0; n];
+            for y in 0..height {
+                for x in 0..width {
+                    let p = (y * width + x) as usize;
+                    let sigma = self.sigma_luminance * blurred[p].max(0.0).sqrt();
+                    let (mut sum, mut sum_weight, mut sum_variance) = (Color::zero(), 0.0, 0.0);
+                    for dy in -2..=2i64 {
+                        for dx in -2..=2i64 {
+                            let (qx, qy) = (x + dx * step, y + dy * step);
+                            if qx < 0 || qy < 0 || qx >= width || qy >= height {
+                                continue;
+                            }
+                            let q = (qy * width + qx) as usize;
+                            let h = KERNEL[(dx + 2) as usize] * KERNEL[(dy + 2) as usize];
+                            let w = h * self.edge_weight(aovs, &color, p, q, step as u32, sigma);
+                            sum += color[q] * w;
+                            sum_weight += w;
+                            sum_variance += w * w * variance[q];
+                        }
+                    }
+                    // the center tap always has weight h, so sum_weight > 0
+                    next_color[p] = sum / sum_weight;
+                    next_variance[p] = sum_variance / (sum_weight * sum_weight);
+                }
+            }
+            color = next_color;
+            variance = next_variance;
+        }
+
+        HdrImage::from_fn(film.width, film.height, |i, j| {
+            let k = (j * film.width + i) as usize;
+            let d = demodulate[k];
+            Color::new(color[k].x * d.x, color[k].y * d.y, color[k].z * d.z)
+        })
+    }
+}
+
+// 3x3 binomial blur, the variance estimate of a single pixel is too noisy on its own
+fn blur_3x3(values: &[f64], width: i64, height: i64) -> Vec<f64> {
+    let weights = [0.25, 0.5, 0.25];
+    let mut blurred = vec![0.0; values.len()];
+    for y in 0..height {
+        for x in 0..width {
+            let (mut sum, mut sum_weight) = (0.0, 0.0);
+            for dy in -1..=1i64 {
+                for dx in -1..=1i64 {
+                    let (qx, qy) = (x + dx, y + dy);
+                    if qx < 0 || qy < 0 || qx >= width || qy >= height {
+                        continue;
+                    }
+                    let w = weights[(dx + 1) as usize] * weights[(dy + 1) as usize];
+                    sum += values[(qy * width + qx) as usize] * w;
+                    sum_weight += w;
+                }
+            }
+            blurred[(y * width + x) as usize] = sum / sum_weight;
+        }
+    }
+    blurred
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::sampler::{Independent, Sampler};
+
+    // left half faces the camera with radiance 0.2, right half faces sideways with 0.8,
+    // every pixel gets a few noisy samples
+    fn noisy_scene() -> (Film, Aovs) {
+        let (width, height) = (16, 16);
+        let n = (width * height) as usize;
+        let mut film = Film::new(width, height);
+        let mut aovs = Aovs {
+            width,
+            height,
+            depth: vec![2.0; n],
+            normal: vec![Vec3::new(0.0, 0.0, 1.0); n],
+            albedo: vec![Color::ones() * 0.5; n],
+            material_id: vec![1; n],
+            object_id: vec![1; n],
+            alpha: vec![1.0; n],
+        };
+        let mut sampler = Independent::new(5);
+        for j in 0..height {
+            for i in 0..width {
+                let mean = if i < width / 2 { 0.2 } else { 0.8 };
+                if i >= width / 2 {
+                    aovs.normal[(j * width + i) as usize] = Vec3::new(1.0, 0.0, 0.0);
+                }
+                for _ in 0..4 {
+                    film.add_sample(i, j, Color::ones() * (mean * 2.0 * sampler.next_f64()));
+                }
+            }
+        }
+        (film, aovs)
+    }
+
+    #[test]
+    fn test_less_noise_same_edges() {
+        let (film, aovs) = noisy_scene();
+        let denoised = Denoiser::default().denoise(&film, &aovs);
+        let error = |get: &dyn Fn(u32, u32) -> Color| {
+            let mut error = 0.0;
+            for j in 0..16 {
+                for i in 0..16 {
+                    let mean = if i < 8 { 0.2 } else { 0.8 };
+                    error += (get(i, j).x - mean).powi(2);
+                }
+            }
+            error / 256.0
+        };
+        let before = error(&|i, j| film.pixel(i, j));
+        let after = error(&|i, j| denoised.get(i, j));
+        assert!(after < before / 4.0, "{} vs {}", after, before);
+        // nothing leaks over the normal edge
+        assert!((denoised.get(7, 8).x - 0.2).abs() < 0.1);
+        assert!((denoised.get(8, 8).x - 0.8).abs() < 0.2);
+    }
+
+    #[test]
+    fn test_iterations_stop_at_the_image_size() {
+        let (film, aovs) = noisy_scene();
+        // taps 16 pixels apart are all outside the 16x16 image
+        let four = Denoiser { iterations: 4, ..Denoiser::default() }.denoise(&film, &aovs);
+        for &iterations in &[5, MAX_ITERATIONS, u32::MAX] {
+            let more = Denoiser { iterations, ..Denoiser::default() }.denoise(&film, &aovs);
+            for j in 0..16 {
+                for i in 0..16 {
+                    assert_eq!(more.get(i, j), four.get(i, j));
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,322 @@
+use image::{ImageBuffer, RgbImage};
+
+use crate::camera::Camera;
+use crate::hdr::HdrImage;
+use crate::render::Tile;
+use crate::tonemap::ToneMapping;
+use crate::util::vec3::Color;
+
+pub fn luminance(color: Color) -> f64 {
+    0.2126 * color.x + 0.7152 * color.y + 0.0722 * color.z
+}
+
+// Sum of the samples of a pixel plus the running mean and variance of their
+// luminance (Welford), which tells how converged the pixel is.
+#[derive(Clone, Copy, Debug)]
+pub struct PixelStats {
+    pub sum: Color,
+    pub n: u32,
+    mean: f64,
+    m2: f64,
+}
+
+impl Default for PixelStats {
+    fn default() -> Self {
+        Self { sum: Color::zero(), n: 0, mean: 0.0, m2: 0.0 }
+    }
+}
+
+impl PixelStats {
+    // rebuild from the values returned by parts(), e.g. when reading a checkpoint
+    pub fn from_parts(sum: Color, n: u32, mean: f64, m2: f64) -> Self {
+        Self { sum, n, mean, m2 }
+    }
+
+    pub fn parts(&self) -> (Color, u32, f64, f64) {
+        (self.sum, self.n, self.mean, self.m2)
+    }
+
+    pub fn add(&mut self, color: Color) {
+        self.sum += color;
+        self.n += 1;
+        let l = luminance(color);
+        let delta = l - self.mean;
+        self.mean += delta / self.n as f64;
+        self.m2 += delta * (l - self.mean);
+    }
+
+    // combine the statistics of two disjoint sets of samples (Chan et al.)
+    pub fn merge(&mut self, other: &PixelStats) {
+        if other.n == 0 {
+            return;
+        }
+        let n = self.n + other.n;
+        let delta = other.mean - self.mean;
+        self.mean += delta * other.n as f64 / n as f64;
+        self.m2 += other.m2 + delta * delta * self.n as f64 * other.n as f64 / n as f64;
+        self.sum += other.sum;
+        self.n = n;
+    }
+
+    pub fn variance(&self) -> f64 {
+        if self.n < 2 {
+            return 0.0;
+        }
+        self.m2 / (self.n - 1) as f64
+    }
+
+    // standard error of the mean luminance relative to the mean itself
+    pub fn relative_error(&self) -> f64 {
+        if self.n < 2 {
+            return f64::INFINITY;
+        }
+        (self.variance() / self.n as f64).sqrt() / self.mean.max(1e-3)
+    }
+}
+
+// How the samples of a pixel become its value. The mean converges to the right answer, but
+// a single rare, very bright sample (a firefly) shows for thousands of samples. Median of
+// means splits the samples into batches by sample index and takes the median of the batch
+// means, which ignores a few outliers at the price of a slight darkening bias.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Estimator {
+    Mean,
+    MedianOfMeans,
+}
+
+pub const MEDIAN_OF_MEANS_BATCHES: u32 = 8;
+
+impl Estimator {
+    pub const ALL: [Estimator; 2] = [Estimator::Mean, Estimator::MedianOfMeans];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            Estimator::Mean => "mean",
+            Estimator::MedianOfMeans => "median-of-means",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Result<Self, String> {
+        Self::ALL
+            .iter()
+            .find(|estimator| estimator.name() == name)
+            .copied()
+            .ok_or_else(|| format!("unknown pixel estimator: {}", name))
+    }
+
+    // splats kept per pixel, sample k goes to batch k % batches()
+    pub fn batches(&self) -> u32 {
+        match self {
+            Estimator::Mean => 1,
+            Estimator::MedianOfMeans => MEDIAN_OF_MEANS_BATCHES,
+        }
+    }
+}
+
+// Filter weighted sum of the samples splatted into a pixel.
+#[derive(Clone, Copy, Debug)]
+pub struct Splat {
+    pub sum: Color,
+    pub weight: f64,
+}
+
+impl Default for Splat {
+    fn default() -> Self {
+        Self { sum: Color::zero(), weight: 0.0 }
+    }
+}
+
+impl Splat {
+    pub fn add(&mut self, color: Color, weight: f64) {
+        self.sum += color * weight;
+        self.weight += weight;
+    }
+}
+
+// What rendering a tile adds to the film: the statistics of the samples taken in its own
+// pixels, and their filtered splats over `region`, the tile grown by the filter margin.
+// Both are stored row by row, with one splat per estimator batch in every pixel.
+pub struct TileSamples {
+    pub stats: Vec<PixelStats>,
+    pub region: Tile,
+    pub splats: Vec<Splat>,
+}
+
+// Linear radiance accumulated per pixel: the statistics of the samples taken in the pixel,
+// which tell how converged it is, and the filtered splats that make up the image.
+#[derive(Clone)]
+pub struct Film {
+    pub width: u32,
+    pub height: u32,
+    estimator: Estimator,
+    pixels: Vec<PixelStats>,
+    splats: Vec<Splat>, // estimator.batches() per pixel
+}
+
+impl Film {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self::with_estimator(width, height, Estimator::Mean)
+    }
+
+    pub fn with_estimator(width: u32, height: u32, estimator: Estimator) -> Self {
+        Self {
+            width,
+            height,
+            estimator,
+            pixels: vec![PixelStats::default(); (width * height) as usize],
+            splats: vec![Splat::default(); (width * height * estimator.batches()) as usize],
+        }
+    }
+
+    pub fn from_pixels(width: u32, height: u32, estimator: Estimator, pixels: Vec<PixelStats>, splats: Vec<Splat>) -> Self {
+        assert_eq!(pixels.len(), (width * height) as usize, "pixel count does not match film size");
+        assert_eq!(splats.len(), (width * height * estimator.batches()) as usize, "splat count does not match film size");
+        Self { width, height, estimator, pixels, splats }
+    }
+
+    pub fn estimator(&self) -> Estimator {
+        self.estimator
+    }
+
+    // all pixels, row by row
+    pub fn pixels(&self) -> &[PixelStats] {
+        &self.pixels
+    }
+
+    pub fn splats(&self) -> &[Splat] {
+        &self.splats
+    }
+
+    fn index(&self, i: u32, j: u32) -> usize {
+        (j * self.width + i) as usize
+    }
+
+    // a sample that only counts for its own pixel, as with the default box filter
+    pub fn add_sample(&mut self, i: u32, j: u32, color: Color) {
+        let index = self.index(i, j);
+        let batches = self.estimator.batches();
+        let batch = (self.pixels[index].n % batches) as usize;
+        self.pixels[index].add(color);
+        self.splats[index * batches as usize + batch].add(color, 1.0);
+    }
+
+    pub fn add_tile(&mut self, tile: Tile, samples: &TileSamples) {
+        for j in 0..tile.height {
+            for i in 0..tile.width {
+                let index = self.index(tile.x + i, tile.y + j);
+                self.pixels[index].merge(&samples.stats[(j * tile.width + i) as usize]);
+            }
+        }
+        let region = samples.region;
+        let batches = self.estimator.batches() as usize;
+        for j in 0..region.height {
+            for i in 0..region.width {
+                let index = self.index(region.x + i, region.y + j) * batches;
+                let from = (j * region.width + i) as usize * batches;
+                for (splat, add) in self.splats[index..index + batches].iter_mut().zip(&samples.splats[from..from + batches]) {
+                    splat.sum += add.sum;
+                    splat.weight += add.weight;
+                }
+            }
+        }
+    }
+
+    pub fn stats(&self, i: u32, j: u32) -> &PixelStats {
+        &self.pixels[self.index(i, j)]
+    }
+
+    pub fn samples(&self, i: u32, j: u32) -> u32 {
+        self.stats(i, j).n
+    }
+
+    // filtered radiance, black until samples have been splatted into the pixel
+    pub fn pixel(&self, i: u32, j: u32) -> Color {
+        let batches = self.estimator.batches() as usize;
+        let index = self.index(i, j) * batches;
+        let splats = &self.splats[index..index + batches];
+        match self.estimator {
+            Estimator::Mean => {
+                let (sum, weight) = splats.iter().fold((Color::zero(), 0.0), |(s, w), splat| (s + splat.sum, w + splat.weight));
+                if weight == 0.0 {
+                    return Color::zero();
+                }
+                sum / weight
+            }
+            Estimator::MedianOfMeans => median_of_means(splats),
+        }
+    }
+
+    // number of samples per pixel from blue (none) to red (max_samples)
+    pub fn sample_heatmap(&self, max_samples: u32) -> RgbImage {
+        ImageBuffer::from_fn(self.width, self.height, |i, j| {
+            let t = (self.samples(i, j) as f64 / max_samples as f64).min(1.0);
+            Camera::color2rgb(Color::new(t, 1.0 - (2.0 * t - 1.0).abs(), 1.0 - t))
+        })
+    }
+
+    // linear radiance as 32 bit floats, for the HDR formats
+    pub fn to_hdr(&self) -> HdrImage {
+        HdrImage::from_fn(self.width, self.height, |i, j| self.pixel(i, j))
+    }
+
+    pub fn to_image(&self, tone_mapping: &ToneMapping) -> RgbImage {
+        self.to_hdr().to_image(tone_mapping)
+    }
+}
+
+// the batch mean with the median luminance, the middle two averaged for an even count
+fn median_of_means(splats: &[Splat]) -> Color {
+    let mut means: Vec<Color> = splats.iter().filter(|s| s.weight > 0.0).map(|s| s.sum / s.weight).collect();
+    if means.is_empty() {
+        return Color::zero();
+    }
+    means.sort_by(|a, b| luminance(*a).partial_cmp(&luminance(*b)).unwrap_or(std::cmp::Ordering::Equal));
+    let middle = means.len() / 2;
+    if 2 * middle == means.len() {
+        (means[middle - 1] + means[middle]) * 0.5
+    } else {
+        means[middle]
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_pixel_stats_merge() {
+        let values = [0.1, 0.5, 0.2, 0.9, 0.4, 0.7];
+        let mut all = PixelStats::default();
+        let mut first = PixelStats::default();
+        let mut second = PixelStats::default();
+        for (k, &v) in values.iter().enumerate() {
+            let color = Color::ones() * v;
+            all.add(color);
+            if k < 2 {
+                first.add(color);
+            } else {
+                second.add(color);
+            }
+        }
+        first.merge(&second);
+        assert_eq!(first.n, 6);
+        assert!((first.variance() - all.variance()).abs() < 1e-12);
+        let mean = values.iter().sum::<f64>() / 6.0;
+        let variance = values.iter().map(|v| (v - mean) * (v - mean)).sum::<f64>() / 5.0;
+        assert!((all.variance() - variance).abs() < 1e-12);
+    }
+
+    #[test]
+    fn test_median_of_means_ignores_fireflies() {
+        let mut mean = Film::new(1, 1);
+        let mut robust = Film::with_estimator(1, 1, Estimator::MedianOfMeans);
+        for k in 0..64 {
+            let color = if k == 13 { Color::ones() * 1000.0 } else { Color::ones() * 0.5 };
+            mean.add_sample(0, 0, color);
+            robust.add_sample(0, 0, color);
+        }
+        assert!(mean.pixel(0, 0).x > 10.0);
+        assert!((robust.pixel(0, 0).x - 0.5).abs() < 1e-9);
+        assert_eq!(robust.samples(0, 0), 64);
+    }
+}
@@ -0,0 +1,159 @@
+use std::f64::consts::PI;
+
+// Pixel reconstruction filters. Every sample is splatted into all pixels whose center lies
+// within the filter radius, weighted by the filter at the offset from that center, and a
+// pixel is the weighted average of what it got. A box of radius 0.5 is the plain average of
+// the samples inside the pixel; wider filters trade sharpness for less aliasing, and the
+// negative lobes of Mitchell and Lanczos sharpen edges again.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum FilterKind {
+    Box,
+    Tent,
+    Gaussian,
+    Mitchell,
+    Lanczos,
+}
+
+impl FilterKind {
+    pub const ALL: [FilterKind; 5] = [
+        FilterKind::Box,
+        FilterKind::Tent,
+        FilterKind::Gaussian,
+        FilterKind::Mitchell,
+        FilterKind::Lanczos,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            FilterKind::Box => "box",
+            FilterKind::Tent => "tent",
+            FilterKind::Gaussian => "gaussian",
+            FilterKind::Mitchell => "mitchell",
+            FilterKind::Lanczos => "lanczos",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Result<Self, String> {
+        Self::ALL
+            .iter()
+            .find(|kind| kind.name() == name)
+            .copied()
+            .ok_or_else(|| format!("unknown filter: {}", name))
+    }
+
+    pub fn default_radius(&self) -> f64 {
+        match self {
+            FilterKind::Box => 0.5,
+            FilterKind::Tent => 1.0,
+            FilterKind::Gaussian => 1.5,
+            FilterKind::Mitchell => 2.0,
+            FilterKind::Lanczos => 3.0,
+        }
+    }
+}
+
+// Every sample is splatted over (2 * radius)^2 pixels and tiles grow by the radius, wider
+// filters only blur the image.
+pub const MAX_FILTER_RADIUS: f64 = 16.0;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Filter {
+    pub kind: FilterKind,
+    pub radius: f64, // in pixels
+}
+
+impl Default for Filter {
+    fn default() -> Self {
+        Self::new(FilterKind::Box, FilterKind::Box.default_radius()).unwrap()
+    }
+}
+
+impl Filter {
+    pub fn new(kind: FilterKind, radius: f64) -> Result<Self, String> {
+        if !(radius > 0.0 && radius <= MAX_FILTER_RADIUS) {
+            return Err(format!("filter radius must be positive and at most {}, got {}", MAX_FILTER_RADIUS, radius));
+        }
+        Ok(Self { kind, radius })
+    }
+
+    // weight of a sample at offset (dx, dy) from a pixel center, separable in x and y
+    pub fn evaluate(&self, dx: f64, dy: f64) -> f64 {
+        self.evaluate_1d(dx) * self.evaluate_1d(dy)
+    }
+
+    // zero outside [-radius, radius), half open so a box of radius 0.5 covers every
+    // position exactly once
+    fn evaluate_1d(&self, x: f64) -> f64 {
+        let r = self.radius;
+        if x < -r || x >= r {
+            return 0.0;
+        }
+        match self.kind {
+            FilterKind::Box => 1.0,
+            FilterKind::Tent => 1.0 - x.abs() / r,
+            FilterKind::Gaussian => {
+                // sigma of a third of the radius, shifted down to reach zero at the radius
+                let sigma = r / 3.0;
+                let gaussian = |x: f64| (-x * x / (2.0 * sigma * sigma)).exp();
+                gaussian(x) - gaussian(r)
+            }
+            FilterKind::Mitchell => mitchell_1d(2.0 * x / r, 1.0 / 3.0, 1.0 / 3.0),
+            FilterKind::Lanczos => sinc(x) * sinc(x / r),
+        }
+    }
+
+    // how many pixels beyond its own a sample can reach
+    pub fn margin(&self) -> u32 {
+        (self.radius - 0.5).max(0.0).ceil() as u32
+    }
+}
+
+// Mitchell-Netravali cubic on [-2, 2], B = C = 1/3 is the recommended compromise
+fn mitchell_1d(x: f64, b: f64, c: f64) -> f64 {
+    let x = x.abs();
+    if x < 1.0 {
+        ((12.0 - 9.0 * b - 6.0 * c) * x * x * x + (-18.0 + 12.0 * b + 6.0 * c) * x * x + (6.0 - 2.0 * b)) / 6.0
+    } else if x < 2.0 {
+        ((-b - 6.0 * c) * x * x * x + (6.0 * b + 30.0 * c) * x * x + (-12.0 * b - 48.0 * c) * x + (8.0 * b + 24.0 * c))
+            / 6.0
+    } else {
+        0.0
+    }
+}
+
+fn sinc(x: f64) -> f64 {
+    if x.abs() < 1e-5 {
+        return 1.0;
+    }
+    (PI * x).sin() / (PI * x)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_filter_shapes() {
+        for &kind in &FilterKind::ALL {
+            let filter = Filter::new(kind, kind.default_radius()).unwrap();
+            assert!(filter.evaluate(0.0, 0.0) > 0.0, "{:?}", kind);
+            assert_eq!(filter.evaluate(filter.radius, 0.0), 0.0, "{:?}", kind);
+            assert_eq!(filter.evaluate(0.0, -filter.radius - 0.1), 0.0, "{:?}", kind);
+            assert!((filter.evaluate(0.3, 0.2) - filter.evaluate(-0.3, -0.2)).abs() < 1e-12, "{:?}", kind);
+        }
+        let mitchell = Filter::new(FilterKind::Mitchell, 2.0).unwrap();
+        assert!(mitchell.evaluate(1.5, 0.0) < 0.0);
+        assert!(Filter::new(FilterKind::Tent, 0.0).is_err());
+        assert!(Filter::new(FilterKind::Tent, f64::NAN).is_err());
+        assert!(Filter::new(FilterKind::Gaussian, f64::INFINITY).is_err());
+        assert!(Filter::new(FilterKind::Gaussian, 1e300).is_err());
+        assert!(Filter::new(FilterKind::Lanczos, MAX_FILTER_RADIUS).is_ok());
+    }
+
+    #[test]
+    fn test_margin() {
+        assert_eq!(Filter::default().margin(), 0);
+        assert_eq!(Filter::new(FilterKind::Tent, 1.0).unwrap().margin(), 1);
+        assert_eq!(Filter::new(FilterKind::Lanczos, 3.0).unwrap().margin(), 3);
+    }
+}
@@ -0,0 +1,191 @@
+// Linear, scene referred images and the file formats that keep them that way. OpenEXR
+// and Radiance .hdr store the radiance as rendered, highlights above 1.0 included, for
+// compositing; any other extension is an 8 bit display export through the image crate.
+
+use image::{ImageBuffer, RgbImage};
+use std::fs;
+use std::path::Path;
+
+use crate::tonemap::ToneMapping;
+use crate::util::vec3::Color;
+
+#[derive(Clone, Debug)]
+pub struct HdrImage {
+    pub width: u32,
+    pub height: u32,
+    pixels: Vec<[f32; 3]>, // linear rgb, row by row
+}
+
+impl HdrImage {
+    pub fn new(width: u32, height: u32) -> Self {
+        Self { width, height, pixels: vec![[0.0; 3]; (width * height) as usize] }
+    }
+
+    pub fn from_fn(width: u32, height: u32, f: impl Fn(u32, u32) -> Color) -> Self {
+        let mut image = Self::new(width, height);
+        for j in 0..height {
+            for i in 0..width {
+                image.set(i, j, f(i, j));
+            }
+        }
+        image
+    }
+
+    pub fn get(&self, i: u32, j: u32) -> Color {
+        let [r, g, b] = self.pixels[(j * self.width + i) as usize];
+        Color::new(r as f64, g as f64, b as f64)
+    }
+
+    pub fn set(&mut self, i: u32, j: u32, color: Color) {
+        self.pixels[(j * self.width + i) as usize] = [color.x as f32, color.y as f32, color.z as f32];
+    }
+
+    // one plane per channel, e.g. for writing them as separate EXR channels
+    pub fn channel(&self, c: usize) -> Vec<f32> {
+        self.pixels.iter().map(|p| p[c]).collect()
+    }
+
+    pub fn to_image(&self, tone_mapping: &ToneMapping) -> RgbImage {
+        ImageBuffer::from_fn(self.width, self.height, |i, j| tone_mapping.to_rgb(self.get(i, j)))
+    }
+
+    // The format is picked by the extension: .exr, .hdr, or anything the image crate writes.
+    // Only the display image is tone mapped, the HDR formats keep the radiance as it is.
+    pub fn save(&self, path: &str, tone_mapping: &ToneMapping) -> Result<(), String> {
+        let extension = Path::new(path).extension().and_then(|e| e.to_str()).unwrap_or("");
+        let bytes = match extension.to_ascii_lowercase().as_str() {
+            "exr" => {
+                let channels = [("R", self.channel(0)), ("G", self.channel(1)), ("B", self.channel(2))];
+                let channels: Vec<(&str, &[f32])> = channels.iter().map(|(name, data)| (*name, &data[..])).collect();
+                encode_exr(self.width, self.height, &channels)
+            }
+            "hdr" => encode_hdr(self),
+            _ => return self.to_image(tone_mapping).save(path).map_err(|e| format!("failed to write {}: {}", path, e)),
+        };
+        fs::write(path, bytes).map_err(|e| format!("failed to write {}: {}", path, e))
+    }
+}
+
+fn push_attribute(bytes: &mut Vec<u8>, name: &str, kind: &str, value: &[u8]) {
+    bytes.extend_from_slice(name.as_bytes());
+    bytes.push(0);
+    bytes.extend_from_slice(kind.as_bytes());
+    bytes.push(0);
+    bytes.extend_from_slice(&(value.len() as i32).to_le_bytes());
+    bytes.extend_from_slice(value);
+}
+
+// Single part scanline OpenEXR without compression, every channel stored as 32 bit float.
+// Channel names may carry a layer prefix like "albedo.R"; they are sorted as the format requires.
+pub fn encode_exr(width: u32, height: u32, channels: &[(&str, &[f32])]) -> Vec<u8> {
+    let mut channels = channels.to_vec();
+    channels.sort_by(|a, b| a.0.cmp(b.0));
+    for (name, data) in &channels {
+        assert_eq!(data.len(), (width * height) as usize, "channel {} does not match the image size", name);
+    }
+
+    let mut bytes = vec![0x76, 0x2f, 0x31, 0x01, 2, 0, 0, 0];
+    let mut list = Vec::new();
+    for (name, _) in &channels {
+        list.extend_from_slice(name.as_bytes());
+        list.push(0);
+        list.extend_from_slice(&2i32.to_le_bytes()); // FLOAT
+        list.extend_from_slice(&[0, 0, 0, 0]); // pLinear and reserved
+        list.extend_from_slice(&1i32.to_le_bytes()); // x sampling
+        list.extend_from_slice(&1i32.to_le_bytes()); // y sampling
+    }
+    list.push(0);
+    push_attribute(&mut bytes, "channels", "chlist", &list);
+    push_attribute(&mut bytes, "compression", "compression", &[0]);
+    let mut window = Vec::new();
+    for value in &[0, 0, width as i32 - 1, height as i32 - 1] {
+        window.extend_from_slice(&value.to_le_bytes());
+    }
+    push_attribute(&mut bytes, "dataWindow", "box2i", &window);
+    push_attribute(&mut bytes, "displayWindow", "box2i", &window);
+    push_attribute(&mut bytes, "lineOrder", "lineOrder", &[0]);
+    push_attribute(&mut bytes, "pixelAspectRatio", "float", &1.0f32.to_le_bytes());
+    push_attribute(&mut bytes, "screenWindowCenter", "v2f", &[0; 8]);
+    push_attribute(&mut bytes, "screenWindowWidth", "float", &1.0f32.to_le_bytes());
+    bytes.push(0);
+
+    // offset table with one entry per scanline, then the scanlines with their channels one after another
+    let line_size = (channels.len() as u32 * width * 4) as u64;
+    let first_line = bytes.len() as u64 + 8 * height as u64;
+    for j in 0..height as u64 {
+        bytes.extend_from_slice(&(first_line + j * (8 + line_size)).to_le_bytes());
+    }
+    for j in 0..height {
+        bytes.extend_from_slice(&(j as i32).to_le_bytes());
+        bytes.extend_from_slice(&(line_size as i32).to_le_bytes());
+        for (_, data) in &channels {
+            for value in &data[(j * width) as usize..((j + 1) * width) as usize] {
+                bytes.extend_from_slice(&value.to_le_bytes());
+            }
+        }
+    }
+    bytes
+}
+
+// shared exponent encoding of Radiance files, negative values are stored as zero
+fn rgbe(color: [f32; 3]) -> [u8; 4] {
+    let [r, g, b] = [color[0].max(0.0), color[1].max(0.0), color[2].max(0.0)];
+    let v = r.max(g).max(b);
+    if v.is_nan() || v < 1e-32 {
+        return [0; 4];
+    }
+    let exponent = v.log2().floor() as i32 + 1;
+    let scale = 2f32.powi(8 - exponent);
+    let byte = |x: f32| (x * scale).min(255.0) as u8;
+    [byte(r), byte(g), byte(b), (exponent + 128) as u8]
+}
+
+// Radiance RGBE with flat (not run length encoded) scanlines, top row first
+pub fn encode_hdr(image: &HdrImage) -> Vec<u8> {
+    let header = format!("#?RADIANCE\nFORMAT=32-bit_rle_rgbe\n\n-Y {} +X {}\n", image.height, image.width);
+    let mut bytes = header.into_bytes();
+    for pixel in &image.pixels {
+        bytes.extend_from_slice(&rgbe(*pixel));
+    }
+    bytes
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_rgbe() {
+        assert_eq!(rgbe([0.0, 0.0, 0.0]), [0, 0, 0, 0]);
+        assert_eq!(rgbe([1.0, 0.5, 0.0]), [128, 64, 0, 129]);
+        // highlights keep their value instead of clipping at 1
+        let [r, _, _, e] = rgbe([3.0, 0.0, 0.0]);
+        assert_eq!(r as f32 * 2f32.powi(e as i32 - 136), 3.0);
+    }
+
+    #[test]
+    fn test_exr_layout() {
+        let (width, height) = (3, 2);
+        let image = HdrImage::from_fn(width, height, |i, j| Color::new(i as f64, j as f64, 4.5));
+        let red = image.channel(0);
+        let blue = image.channel(2);
+        let bytes = encode_exr(width, height, &[("R", &red), ("B", &blue)]);
+        assert_eq!(&bytes[..4], &[0x76, 0x2f, 0x31, 0x01]);
+
+        // channels are sorted, so the last line ends with the red values of the bottom row
+        let end = bytes.len();
+        let last_red: Vec<f32> = (0..3)
+            .map(|k| {
+                let at = end - 12 + 4 * k;
+                f32::from_le_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]])
+            })
+            .collect();
+        assert_eq!(last_red, vec![0.0, 1.0, 2.0]);
+
+        // the last offset points at the last line: y, size, then 2 channels of 3 floats
+        let table = end - 2 * (8 + 24) - 16;
+        let mut offset = [0; 8];
+        offset.copy_from_slice(&bytes[table + 8..table + 16]);
+        assert_eq!(u64::from_le_bytes(offset) as usize, end - (8 + 24));
+    }
+}
@@ -1,25 +1,42 @@
 pub mod sphere;
 pub mod quad;
+pub mod constant_medium;
+pub mod grid_medium;
+pub mod moving;
 
 use crate::util::ray::Ray;
 use crate::util::interval::Interval;
 use crate::util::vec3::{Point3, Vec3};
 use crate::material::Material;
 use crate::util::bvh::AABB;
+use crate::util::sampler::Sampler;
 
 
 pub trait Hittable: Send + Sync {
-    fn hit(&self, ray: &Ray, rot: &Interval) -> Option<HitRecord>;
+    // media use the sampler to pick a scattering distance
+    fn hit(&self, ray: &Ray, rot: &Interval, sampler: &mut dyn Sampler) -> Option<HitRecord>;
 
     fn bbox(&self) -> AABB;
 }
+
+impl<T: Hittable + ?Sized> Hittable for Box<T> {
+    fn hit(&self, ray: &Ray, rot: &Interval, sampler: &mut dyn Sampler) -> Option<HitRecord> {
+        (**self).hit(ray, rot, sampler)
+    }
+
+    fn bbox(&self) -> AABB {
+        (**self).bbox()
+    }
+}
 #[derive(Clone, Copy)]
 pub struct HitRecord<'a> {
     pub point: Point3,
     pub t: f64,
     pub normal: Vec3, // normal vector of the hit point
     pub is_outward: bool,
-    pub material: &'a dyn Material
+    pub material: &'a dyn Material,
+    pub object_id: u32,   // set by the BVH, 0 until then
+    pub material_id: u32, // set by the BVH, 0 until then
 }
 
 
@@ -37,6 +54,8 @@ impl<'a> HitRecord<'a> {
             normal,
             is_outward,
             material,
+            object_id: 0,
+            material_id: 0,
         }
     }
 
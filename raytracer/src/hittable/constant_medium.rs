@@ -0,0 +1,102 @@
+use super::HitRecord;
+use super::Hittable;
+use super::Vec3;
+use crate::material::isotropic::Isotropic;
+use crate::util::bvh::AABB;
+use crate::util::interval::Interval;
+use crate::util::ray::Ray;
+use crate::util::vec3::Color;
+use crate::util::sampler::Sampler;
+
+// Volume of constant density filling a closed boundary, e.g. smoke in a sphere.
+// A ray travelling through it scatters after an exponentially distributed distance.
+pub struct ConstantMedium<H: Hittable> {
+    boundary: H,
+    neg_inv_density: f64,
+    phase_function: Isotropic,
+}
+
+impl<H: Hittable> ConstantMedium<H> {
+    pub fn new(boundary: H, density: f64, albedo: Color) -> Self {
+        Self {
+            boundary,
+            neg_inv_density: -1.0 / density,
+            phase_function: Isotropic::new(albedo),
+        }
+    }
+}
+
+impl<H: Hittable> Hittable for ConstantMedium<H> {
+    fn hit(&self, ray: &Ray, rot: &Interval, sampler: &mut dyn Sampler) -> Option<HitRecord> {
+        // an empty medium would give -inf * 0 below for a random number of 0
+        if self.neg_inv_density == f64::NEG_INFINITY {
+            return None;
+        }
+        let everywhere = Interval::new(f64::NEG_INFINITY, f64::INFINITY);
+        let enter = self.boundary.hit(ray, &everywhere, sampler)?;
+        let exit = self.boundary.hit(ray, &Interval::new(enter.t + 0.0001, f64::INFINITY), sampler)?;
+
+        let t_enter = f64::max(enter.t, rot.tmin).max(0.0);
+        let t_exit = f64::min(exit.t, rot.tmax);
+        if t_enter >= t_exit {
+            return None;
+        }
+
+        let ray_length = ray.dir.length();
+        let distance_inside = (t_exit - t_enter) * ray_length;
+        let hit_distance = self.neg_inv_density * (1.0 - sampler.next_f64()).ln();
+        if hit_distance > distance_inside {
+            return None;
+        }
+
+        let t = t_enter + hit_distance / ray_length;
+        Some(HitRecord::new(
+            ray.at(t),
+            t,
+            Vec3::new(1.0, 0.0, 0.0), // arbitrary, the phase function ignores it
+            true,
+            &self.phase_function,
+        ))
+    }
+
+    fn bbox(&self) -> AABB {
+        self.boundary.bbox()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hittable::sphere::Sphere;
+    use crate::material::diffusive::Diffusive;
+    use crate::util::sampler::Independent;
+    use crate::util::vec3::Point3;
+
+    fn smoke(density: f64) -> ConstantMedium<Sphere<Diffusive>> {
+        let boundary = Sphere::new(Point3::zero(), 100.0, Diffusive::new(Color::ones()));
+        ConstantMedium::new(boundary, density, Color::ones())
+    }
+
+    #[test]
+    fn test_mean_free_path() {
+        let medium = smoke(0.5);
+        let ray = Ray::new(Point3::zero(), Vec3::new(0.0, 0.6, 0.8));
+        let rot = Interval::new(0.0, f64::INFINITY);
+        let mut sampler = Independent::new(9);
+        let n = 20000;
+        let mut total = 0.0;
+        for _ in 0..n {
+            total += medium.hit(&ray, &rot, &mut sampler).unwrap().t;
+        }
+        assert!((total / n as f64 - 2.0).abs() < 0.05, "mean free path {}", total / n as f64);
+    }
+
+    #[test]
+    fn test_empty_medium() {
+        let medium = smoke(0.0);
+        let ray = Ray::new(Point3::new(-200.0, 0.0, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        let rot = Interval::new(0.001, f64::INFINITY);
+        let mut sampler = Independent::new(9);
+        assert!((0..1000).all(|_| medium.hit(&ray, &rot, &mut sampler).is_none()));
+    }
+}
@@ -0,0 +1,119 @@
+use super::HitRecord;
+use super::Hittable;
+use super::Point3;
+use super::Vec3;
+use crate::material::Material;
+use crate::material::henyey_greenstein::HenyeyGreenstein;
+use crate::util::bvh::AABB;
+use crate::util::interval::Interval;
+use crate::util::ray::Ray;
+use crate::util::vec3::Color;
+use crate::util::voxel_grid::VoxelGrid;
+use crate::util::sampler::Sampler;
+use std::sync::Arc;
+
+// phase function of a grid medium, emission is looked up from a grid at the scattering point
+pub struct GridPhase {
+    phase_function: HenyeyGreenstein,
+    emission: Color,
+    emission_grid: Arc<VoxelGrid>,
+    bbox: AABB,
+}
+
+impl Material for GridPhase {
+    fn scatter(&self, ray: &Ray, hit_record: &HitRecord, sampler: &mut dyn Sampler) -> Ray {
+        self.phase_function.scatter(ray, hit_record, sampler)
+    }
+
+    fn attenuation(&self) -> Color {
+        self.phase_function.attenuation()
+    }
+
+    fn emitted(&self, hit_record: &HitRecord) -> Color {
+        self.emission * self.emission_grid.sample(to_grid(&self.bbox, &hit_record.point))
+    }
+}
+
+// map a world space point into the [0, 1]^3 coordinates of the grid
+fn to_grid(bbox: &AABB, p: &Point3) -> Point3 {
+    Point3::new(
+        (p.x - bbox.x.tmin) / (bbox.x.tmax - bbox.x.tmin),
+        (p.y - bbox.y.tmin) / (bbox.y.tmax - bbox.y.tmin),
+        (p.z - bbox.z.tmin) / (bbox.z.tmax - bbox.z.tmin),
+    )
+}
+
+// Heterogeneous medium whose density is given by a voxel grid stretched over a box.
+// Collisions are found with delta tracking against the maximum density of the grid.
+pub struct GridMedium {
+    density_grid: Arc<VoxelGrid>,
+    density_scale: f64,
+    majorant: f64,
+    phase: GridPhase,
+}
+
+impl GridMedium {
+    pub fn new(grid: VoxelGrid, min: Point3, max: Point3, density_scale: f64, albedo: Color, g: f64) -> Self {
+        let bbox = AABB::new_from_points(min, max);
+        let majorant = density_scale * grid.max_value();
+        let density_grid = Arc::new(grid);
+        Self {
+            density_grid: density_grid.clone(),
+            density_scale,
+            majorant,
+            phase: GridPhase {
+                phase_function: HenyeyGreenstein::new(albedo, g),
+                emission: Color::zero(),
+                emission_grid: density_grid,
+                bbox,
+            },
+        }
+    }
+
+    // Let the medium glow, e.g. for fire. The emission is scaled by the emission grid,
+    // or by the density grid when none is given.
+    pub fn set_emission(&mut self, emission: Color, emission_grid: Option<VoxelGrid>) {
+        self.phase.emission = emission;
+        if let Some(grid) = emission_grid {
+            self.phase.emission_grid = Arc::new(grid);
+        }
+    }
+
+    fn density(&self, p: &Point3) -> f64 {
+        self.density_scale * self.density_grid.sample(to_grid(&self.phase.bbox, p))
+    }
+}
+
+impl Hittable for GridMedium {
+    fn hit(&self, ray: &Ray, rot: &Interval, sampler: &mut dyn Sampler) -> Option<HitRecord> {
+        if self.majorant <= 0.0 {
+            return None;
+        }
+        let inside = self.phase.bbox.hit_interval(ray, rot)?;
+        let ray_length = ray.dir.length();
+
+        // delta tracking: sample tentative collisions with the majorant and
+        // accept them with probability density / majorant
+        let mut t = inside.tmin;
+        loop {
+            t -= (1.0 - sampler.next_f64()).ln() / (self.majorant * ray_length);
+            if t >= inside.tmax {
+                return None;
+            }
+            let p = ray.at(t);
+            if sampler.next_f64() * self.majorant < self.density(&p) {
+                return Some(HitRecord::new(
+                    p,
+                    t,
+                    Vec3::new(1.0, 0.0, 0.0), // arbitrary, the phase function ignores it
+                    true,
+                    &self.phase,
+                ));
+            }
+        }
+    }
+
+    fn bbox(&self) -> AABB {
+        self.phase.bbox
+    }
+}
@@ -0,0 +1,115 @@
+use super::HitRecord;
+use super::Hittable;
+use super::Vec3;
+use crate::util::bvh::AABB;
+use crate::util::interval::Interval;
+use crate::util::ray::Ray;
+use crate::util::sampler::Sampler;
+
+// Moves any hittable (e.g. a Sphere or a Quad) along a keyframed path. Each keyframe
+// is a (time, offset) pair, offsets are interpolated linearly and held before the
+// first and after the last keyframe.
+pub struct Moving<H: Hittable> {
+    inner: H,
+    keyframes: Vec<(f64, Vec3)>,
+    bbox: AABB,
+}
+
+impl<H: Hittable> Moving<H> {
+    pub fn new(inner: H, mut keyframes: Vec<(f64, Vec3)>) -> Self {
+        assert!(!keyframes.is_empty(), "a moving object needs at least one keyframe");
+        keyframes.sort_by(|a, b| a.0.partial_cmp(&b.0).unwrap());
+        // the motion is linear between keyframes, so the box around all keyframe
+        // positions also covers everything in between
+        let inner_bbox = inner.bbox();
+        let mut bbox = Self::translate(&inner_bbox, keyframes[0].1);
+        for (_, offset) in keyframes.iter().skip(1) {
+            bbox = AABB::new_from_aabb(&bbox, &Self::translate(&inner_bbox, *offset));
+        }
+        Self { inner, keyframes, bbox }
+    }
+
+    // move from offset_0 at time_0 to offset_1 at time_1
+    pub fn new_linear(inner: H, time_0: f64, offset_0: Vec3, time_1: f64, offset_1: Vec3) -> Self {
+        Self::new(inner, vec![(time_0, offset_0), (time_1, offset_1)])
+    }
+
+    fn translate(bbox: &AABB, offset: Vec3) -> AABB {
+        AABB::new(
+            Interval::new(bbox.x.tmin + offset.x, bbox.x.tmax + offset.x),
+            Interval::new(bbox.y.tmin + offset.y, bbox.y.tmax + offset.y),
+            Interval::new(bbox.z.tmin + offset.z, bbox.z.tmax + offset.z),
+        )
+    }
+
+    pub fn offset_at(&self, time: f64) -> Vec3 {
+        let first = self.keyframes[0];
+        if time <= first.0 {
+            return first.1;
+        }
+        for window in self.keyframes.windows(2) {
+            let (time_0, offset_0) = window[0];
+            let (time_1, offset_1) = window[1];
+            if time <= time_1 {
+                let t = (time - time_0) / (time_1 - time_0);
+                return offset_0 * (1.0 - t) + offset_1 * t;
+            }
+        }
+        self.keyframes[self.keyframes.len() - 1].1
+    }
+}
+
+impl<H: Hittable> Hittable for Moving<H> {
+    fn hit(&self, ray: &Ray, rot: &Interval, sampler: &mut dyn Sampler) -> Option<HitRecord> {
+        // move the ray instead of the object
+        let offset = self.offset_at(ray.time);
+        let mut moved_ray = ray.clone();
+        moved_ray.ori -= offset;
+        let mut hit_record = self.inner.hit(&moved_ray, rot, sampler)?;
+        hit_record.point += offset;
+        Some(hit_record)
+    }
+
+    fn bbox(&self) -> AABB {
+        self.bbox
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::hittable::sphere::Sphere;
+    use crate::material::diffusive::Diffusive;
+    use crate::util::sampler::{Independent, DEFAULT_SEED};
+    use crate::util::vec3::Point3;
+
+    #[test]
+    fn test_keyframes() {
+        let sphere = Sphere::new(Point3::zero(), 1.0, Diffusive::new(Vec3::ones()));
+        let moving = Moving::new(
+            sphere,
+            vec![(1.0, Vec3::new(0.0, 2.0, 0.0)), (0.0, Vec3::zero()), (2.0, Vec3::new(4.0, 2.0, 0.0))],
+        );
+        assert_eq!(moving.offset_at(-1.0), Vec3::zero());
+        assert_eq!(moving.offset_at(0.5), Vec3::new(0.0, 1.0, 0.0));
+        assert_eq!(moving.offset_at(1.5), Vec3::new(2.0, 2.0, 0.0));
+        assert_eq!(moving.offset_at(3.0), Vec3::new(4.0, 2.0, 0.0));
+
+        let bbox = moving.bbox();
+        assert!(bbox.x.tmin <= -1.0 && bbox.x.tmax >= 5.0);
+        assert!(bbox.y.tmin <= -1.0 && bbox.y.tmax >= 3.0);
+    }
+
+    #[test]
+    fn test_hit_follows_motion() {
+        let sphere = Sphere::new(Point3::zero(), 1.0, Diffusive::new(Vec3::ones()));
+        let moving = Moving::new_linear(sphere, 0.0, Vec3::zero(), 1.0, Vec3::new(0.0, 10.0, 0.0));
+        let rot = Interval::new(0.001, 100.0);
+        let mut ray = Ray::new(Point3::new(-5.0, 10.0, 0.0), Vec3::new(1.0, 0.0, 0.0));
+        let mut sampler = Independent::new(DEFAULT_SEED);
+        assert!(moving.hit(&ray, &rot, &mut sampler).is_none());
+        ray.time = 1.0;
+        let hit_record = moving.hit(&ray, &rot, &mut sampler).unwrap();
+        assert!((hit_record.point - Point3::new(-1.0, 10.0, 0.0)).length() < 1e-9);
+    }
+}
@@ -6,6 +6,7 @@ use super::Material;
 use crate::util::bvh::AABB;
 use crate::util::interval::Interval;
 use crate::util::ray::Ray;
+use crate::util::sampler::Sampler;
 
 pub struct Quad<T: Material> {
     q: Point3,
@@ -26,7 +27,7 @@ impl<T: Material> Quad<T> {
 }
 
 impl<T: Material> Hittable for Quad<T> {
-    fn hit (&self, ray: &Ray, rot: &Interval) -> Option<HitRecord>{
+    fn hit (&self, ray: &Ray, rot: &Interval, _sampler: &mut dyn Sampler) -> Option<HitRecord>{
         let normal = Vec3::cross(&self.u, &self.v);
         let d = Vec3::dot(&normal, &self.q);
         let t = (d - Vec3::dot(&normal, &ray.ori)) / Vec3::dot(&normal, &ray.dir);
@@ -6,6 +6,7 @@ use super::Material;
 use crate::util::bvh::AABB;
 use crate::util::interval::Interval;
 use crate::util::ray::Ray;
+use crate::util::sampler::Sampler;
 
 pub struct Sphere<T: Material> {
     center: Point3,
@@ -28,7 +29,8 @@ impl<T: Material> Hittable for Sphere<T> {
     fn hit(
         &self, 
         ray: &Ray, 
-        rot: &Interval
+        rot: &Interval,
+        _sampler: &mut dyn Sampler,
     ) -> Option<HitRecord> {
         // calculate the distance between ray origin and sphere center
         let v = self.center - ray.ori;
@@ -1,13 +1,12 @@
 #![allow(clippy::float_cmp)]
 #![feature(box_syntax)]
 
+use std::fmt::Display;
+use std::str::FromStr;
 use std::time::{Duration, Instant};
-use threadpool::ThreadPool;
-use std::sync::mpsc;
 use std::sync::Arc;
 use image::Primitive;
-use image::{ImageBuffer, Rgb, RgbImage};
-use indicatif::ProgressBar;
+use image::{Rgb, RgbImage};
 use rand::Rng;
 
 pub mod camera;
@@ -15,67 +14,272 @@ pub mod hittable;
 pub mod util;
 pub mod world;
 pub mod material;
+pub mod scene;
+pub mod texture;
+pub mod projection;
+pub mod stereo;
+pub mod animation;
+pub mod render;
+pub mod film;
+pub mod checkpoint;
+pub mod filter;
+pub mod hdr;
+pub mod tonemap;
+pub mod aov;
+pub mod denoise;
+pub mod post;
 
 use crate::world::World;
-use camera::Camera;
+use camera::CameraBuilder;
+use checkpoint::Checkpoint;
+use post::{Bloom, Glare, MAX_BLOOM_RADIUS, MAX_GLARE_LENGTH};
+use render::{render_progressive, resume_progressive, Adaptive, RenderSettings};
+use scene::Scene;
+use stereo::{StereoLayout, StereoRig};
+use denoise::{Denoiser, MAX_ITERATIONS};
+use film::Estimator;
+use filter::{Filter, FilterKind};
+use tonemap::ToneOperator;
+use util::sampler::SamplerKind;
 use util::vec3::{Point3, Vec3};
 
-// multi thread rendering only support bvh version
-pub fn render_multi_thread(camera: Camera, n_jobs: usize, n_threads: usize) -> RgbImage{
-    let (tx, rx) = mpsc::channel();
-    let image_width = camera.image_width;
-    let image_height = camera.image_height;
-    let camera_ptr = Arc::new(camera);
-    let pool = ThreadPool::new(n_threads);
-    let bar = ProgressBar::new(n_jobs as u64);
-
-    for k in 0..n_jobs {
-        let tx = tx.clone();
-        let camera_ptr = camera_ptr.clone();
-        pool.execute(move || {
-            let delta_height = camera_ptr.image_height / n_jobs as u32;
-            let start_height = k as u32 * delta_height;
-            let end_height = (k + 1) as u32 * delta_height;
-            let mut _pixel = Point3::new(0.0, 0.0, 0.0);
-            let mut frac: RgbImage = ImageBuffer::new(camera_ptr.image_width, delta_height);
-            for j in start_height..end_height {
-                for i in 0..camera_ptr.image_width{
-                    _pixel = camera_ptr.pixel0_loc + camera_ptr.du * i as f64 + camera_ptr.dv * j as f64;
-                    let color = camera_ptr.get_pixel_color(_pixel);
-                    let color = Camera::linear_to_gamma(color);
-                    let color = Camera::color2rgb(color);
-                    frac.put_pixel(i, j-start_height, color)
-                }
-            }
-            tx.send((start_height, end_height, frac))
-                .expect("failed to send result");
-        })
-    }
+// raytracer [scene.json] [--spp N] [--pass N] [--preview PATH] [--preview-every SECONDS]
+//           [--adaptive MIN_SPP ERROR] [--heatmap PATH] [--budget SECONDS]
+//           [--checkpoint PATH] [--checkpoint-every SECONDS] [--resume PATH] [--seed N]
+//           [--sampler NAME] [--filter NAME] [--filter-radius R] [--threads N] [--tile N]
+//           [--output PATH]... [--exposure EV] [--tonemap NAME] [--aov PATH]
+//           [--denoise ITERATIONS] [--clamp MAX] [--estimator NAME]
+//           [--bloom THRESHOLD] [--bloom-intensity X] [--bloom-radius PIXELS]
+//           [--glare THRESHOLD] [--glare-intensity X] [--glare-length PIXELS] [--glare-points N]
+//           [--vignette STRENGTH] [--chromatic-aberration AMOUNT]
+//           [--stereo LAYOUT | --ods LAYOUT] [--ipd DISTANCE] [--convergence DISTANCE]
+// With --pass the image is rendered progressively and a preview is written after every
+// pass (or every --preview-every seconds) to --preview, output/preview.png by default.
+// With --adaptive pixels stop after MIN_SPP samples once their relative error is below
+// ERROR, --spp is then the maximum.
+// With --budget no new pass is started after the given time and the image is saved as is.
+// With --checkpoint the film is saved after every pass (or every --checkpoint-every seconds);
+// --resume continues from such a file with its sampling settings up to --spp.
+// --sampler is one of independent (default), stratified, halton, sobol or bluenoise.
+// --filter is one of box (default), tent, gaussian, mitchell or lanczos, each with its own
+// default radius in pixels unless --filter-radius is given.
+// --output may be given several times, .exr and .hdr files keep the linear radiance and
+// anything else is a display image; output/test3.png by default. Animated scenes write
+// every frame to every output with the frame number added, e.g. output/test3_0001.png,
+// and cannot be resumed, checkpointed or denoised, nor have AOVs or stereo.
+// Display images and previews are exposed by --exposure stops and tone mapped with one of
+// none (clip at 1, default), reinhard, aces or agx.
+// --aov also writes depth, normals, albedo, material and object IDs and alpha, as layers
+// next to the beauty image if PATH is an .exr file, else as PATH_depth.png and so on.
+// --denoise filters the image guided by the AOVs before it is written, 5 iterations are
+// a good start and 16 the most.
+// Against fireflies, --clamp limits the radiance of every sample (a few times the brightest
+// light is a good start) and --estimator median-of-means replaces the default mean with a
+// median over batches of samples.
+// --bloom and --glare scatter light brighter than THRESHOLD into a halo (--bloom-radius up
+// to 32 pixels) and a star of streaks up to 100 pixels long, --vignette darkens the corners
+// (1 is strong) and --chromatic-aberration separates red and blue towards the edges (0.005
+// is subtle, it must stay between -2 and 2). They are applied to every output and preview
+// before tone mapping.
+// --stereo renders a left and a right eye --ipd apart (0.065 by default), side-by-side or
+// top-bottom in one image, with parallel eyes unless --convergence gives the distance
+// where they meet. --ods does the same for equirectangular panoramas, which it requires,
+// with omni-directional stereo. Stereo pairs cannot be resumed, checkpointed or denoised and
+// have no AOVs.
+struct Options {
+    scene: Option<Scene>,
+    resume_path: Option<String>,
+    outputs: Vec<String>,
+    aov_path: Option<String>,
+    denoiser: Option<Denoiser>,
+    stereo: Option<StereoRig>,
+    settings: RenderSettings,
+}
+
+// the value of an option, with the option in the error
+fn parse<T: FromStr>(arg: &str, value: &str) -> Result<T, String>
+where
+    T::Err: Display,
+{
+    value.parse().map_err(|e| format!("invalid value for {}: {}", arg, e))
+}
 
-    let mut result: RgbImage = ImageBuffer::new(image_width, image_height);
+// Duration::from_secs_f64 panics on negative and infinite values
+fn parse_seconds(arg: &str, value: &str) -> Result<Duration, String> {
+    let seconds: f64 = parse(arg, value)?;
+    if !(seconds >= 0.0 && seconds.is_finite()) {
+        return Err(format!("invalid value for {}: {} is not a duration in seconds", arg, value));
+    }
+    Ok(Duration::from_secs_f64(seconds))
+}
 
-    for (start_height, end_height, frac) in rx.iter().take(n_jobs) {
-        for j in start_height..end_height {
-            for i in 0..image_width {
-                let j = j as u32;
-                let i = i as u32;
-                *result.get_pixel_mut(i, j) = *frac.get_pixel(i, j-start_height);
+fn parse_args() -> Result<Options, String> {
+    let mut scene_path = None;
+    let mut resume_path = None;
+    let mut outputs = Vec::new();
+    let mut aov_path = None;
+    let mut denoiser = None;
+    let mut filter_kind = FilterKind::Box;
+    let mut filter_radius = None;
+    let (mut bloom, mut glare) = (None, None);
+    let (mut bloom_intensity, mut bloom_radius) = (None, None);
+    let (mut glare_intensity, mut glare_length, mut glare_points) = (None, None, None);
+    let (mut stereo_layout, mut omnidirectional) = (None, false);
+    let (mut ipd, mut convergence): (f64, f64) = (0.065, f64::INFINITY);
+    let mut settings = RenderSettings::default();
+    let mut progressive = false;
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if !arg.starts_with("--") {
+            scene_path = Some(arg);
+            continue;
+        }
+        let value = args.next().ok_or_else(|| format!("missing value for {}", arg))?;
+        match arg.as_str() {
+            "--spp" => settings.samples_per_pixel = parse(&arg, &value)?,
+            "--pass" => {
+                settings.samples_per_pass = parse(&arg, &value)?;
+                progressive = true;
             }
+            "--preview" => settings.preview_path = Some(value),
+            "--preview-every" => settings.preview_interval = Some(parse_seconds(&arg, &value)?),
+            "--adaptive" => {
+                let target = args.next().ok_or_else(|| format!("missing target error for {}", arg))?;
+                settings.adaptive = Some(Adaptive {
+                    min_samples: parse(&arg, &value)?,
+                    target_error: parse(&arg, &target)?,
+                });
+            }
+            "--budget" => settings.time_budget = Some(parse_seconds(&arg, &value)?),
+            "--checkpoint" => settings.checkpoint_path = Some(value),
+            "--checkpoint-every" => settings.checkpoint_interval = Some(parse_seconds(&arg, &value)?),
+            "--resume" => resume_path = Some(value),
+            "--output" => outputs.push(value),
+            "--aov" => aov_path = Some(value),
+            "--denoise" => denoiser = Some(Denoiser { iterations: parse(&arg, &value)?, ..Denoiser::default() }),
+            "--exposure" => settings.tone_mapping.exposure = parse(&arg, &value)?,
+            "--clamp" => settings.max_radiance = Some(parse(&arg, &value)?),
+            "--estimator" => settings.estimator = Estimator::from_name(&value)?,
+            "--bloom" => bloom = Some(Bloom::new(parse(&arg, &value)?)),
+            "--bloom-intensity" => bloom_intensity = Some(parse(&arg, &value)?),
+            "--bloom-radius" => bloom_radius = Some(parse(&arg, &value)?),
+            "--glare" => glare = Some(Glare::new(parse(&arg, &value)?)),
+            "--glare-intensity" => glare_intensity = Some(parse(&arg, &value)?),
+            "--glare-length" => glare_length = Some(parse(&arg, &value)?),
+            "--glare-points" => glare_points = Some(parse(&arg, &value)?),
+            "--vignette" => settings.post.vignette = parse(&arg, &value)?,
+            "--chromatic-aberration" => settings.post.chromatic_aberration = parse(&arg, &value)?,
+            "--stereo" => stereo_layout = Some(StereoLayout::from_name(&value)?),
+            "--ods" => {
+                stereo_layout = Some(StereoLayout::from_name(&value)?);
+                omnidirectional = true;
+            }
+            "--ipd" => ipd = parse(&arg, &value)?,
+            "--convergence" => convergence = parse(&arg, &value)?,
+            "--tonemap" => settings.tone_mapping.operator = ToneOperator::from_name(&value)?,
+            "--heatmap" => settings.heatmap_path = Some(value),
+            "--seed" => settings.seed = parse(&arg, &value)?,
+            "--sampler" => settings.sampler = SamplerKind::from_name(&value)?,
+            "--filter" => filter_kind = FilterKind::from_name(&value)?,
+            "--filter-radius" => filter_radius = Some(parse(&arg, &value)?),
+            "--threads" => settings.n_threads = parse(&arg, &value)?,
+            "--tile" => settings.tile_size = parse(&arg, &value)?,
+            _ => return Err(format!("unknown option {}", arg)),
         }
-        bar.inc(1);
     }
-    bar.finish();
-    result
+    settings.filter = Filter::new(filter_kind, filter_radius.unwrap_or_else(|| filter_kind.default_radius()))?;
+    settings.post.bloom = bloom.map(|bloom: Bloom| Bloom {
+        intensity: bloom_intensity.unwrap_or(bloom.intensity),
+        radius: bloom_radius.unwrap_or(bloom.radius),
+        ..bloom
+    });
+    settings.post.glare = glare.map(|glare: Glare| Glare {
+        intensity: glare_intensity.unwrap_or(glare.intensity),
+        length: glare_length.unwrap_or(glare.length),
+        points: glare_points.unwrap_or(glare.points),
+        ..glare
+    });
+    let bloom_radius_ok = settings.post.bloom.map(|bloom| bloom.radius > 0.0).unwrap_or(true);
+    let glare_ok = settings.post.glare.map(|glare| glare.length > 0.0 && glare.points > 0).unwrap_or(true);
+    if !bloom_radius_ok || !glare_ok {
+        return Err("bloom radius, glare length and glare points must be positive".to_string());
+    }
+    if settings.post.bloom.map(|bloom| bloom.radius > MAX_BLOOM_RADIUS).unwrap_or(false) {
+        return Err(format!("bloom radius must be at most {} pixels", MAX_BLOOM_RADIUS));
+    }
+    if settings.post.glare.map(|glare| glare.length > MAX_GLARE_LENGTH).unwrap_or(false) {
+        return Err(format!("glare length must be at most {} pixels", MAX_GLARE_LENGTH));
+    }
+    // a single NaN spreads over the whole image
+    let mut post_values = vec![settings.post.vignette];
+    if let Some(bloom) = settings.post.bloom {
+        post_values.extend_from_slice(&[bloom.threshold, bloom.intensity]);
+    }
+    if let Some(glare) = settings.post.glare {
+        post_values.extend_from_slice(&[glare.threshold, glare.intensity, glare.rotation]);
+    }
+    if !post_values.iter().all(|value| value.is_finite()) {
+        return Err("bloom, glare and vignette settings must be finite".to_string());
+    }
+    if denoiser.map(|denoiser| denoiser.iterations == 0 || denoiser.iterations > MAX_ITERATIONS).unwrap_or(false) {
+        return Err(format!("denoise iterations must be between 1 and {}", MAX_ITERATIONS));
+    }
+    // blue is magnified by 1 / (1 - amount / 2)
+    let aberration = settings.post.chromatic_aberration;
+    if !(aberration > -2.0 && aberration < 2.0) {
+        return Err("chromatic aberration must be between -2 and 2".to_string());
+    }
+    let stereo = match stereo_layout {
+        Some(_) if !(ipd > 0.0 && ipd.is_finite() && convergence > 0.0) => {
+            return Err("interpupillary distance and convergence distance must be positive".to_string());
+        }
+        Some(_) if resume_path.is_some() || settings.checkpoint_path.is_some() || aov_path.is_some() || denoiser.is_some() => {
+            return Err("stereo pairs cannot be resumed, checkpointed, denoised or written with AOVs".to_string());
+        }
+        Some(layout) if omnidirectional => Some(StereoRig::new_omnidirectional(ipd, layout)),
+        Some(layout) => Some(StereoRig::new(ipd, convergence, layout)),
+        None => None,
+    };
+    if progressive && settings.preview_path.is_none() {
+        settings.preview_path = Some("output/preview.png".to_string());
+    }
+    if !progressive {
+        // adaptive sampling needs passes to decide which pixels are done
+        let needs_passes = settings.time_budget.is_some() || settings.checkpoint_path.is_some();
+        settings.samples_per_pass = match settings.adaptive {
+            Some(adaptive) => adaptive.min_samples.max(1),
+            // the budget is checked and checkpoints are written between passes
+            None if needs_passes => settings.samples_per_pixel.min(16),
+            None => settings.samples_per_pixel,
+        };
+    }
+    if settings.samples_per_pixel == 0 || settings.samples_per_pass == 0 || settings.tile_size == 0 || settings.n_threads == 0 {
+        return Err("sample counts, tile size and thread count must be positive".to_string());
+    }
+    if outputs.is_empty() {
+        outputs.push("output/test3.png".to_string());
+    }
+    // a scene file given on the command line replaces the built-in scene
+    let scene = match scene_path {
+        Some(path) => Some(scene::load_scene(&path)?),
+        None => None,
+    };
+    let animated = scene.as_ref().map(|scene| scene.animation.is_some()).unwrap_or(false);
+    if animated && (stereo.is_some() || resume_path.is_some() || settings.checkpoint_path.is_some() || aov_path.is_some() || denoiser.is_some()) {
+        return Err("animations cannot be rendered in stereo, resumed, checkpointed, denoised or written with AOVs".to_string());
+    }
+    Ok(Options { scene, resume_path, outputs, aov_path, denoiser, stereo, settings })
 }
 
 fn main() {
+    let Options { scene, resume_path, outputs, aov_path, denoiser, stereo, mut settings } = parse_args().unwrap_or_else(|e| panic!("{}", e));
+
     let center = Point3::new(-3.0,0.0, 1.0);
     let look_to = Vec3::new(0.0, 0.0, 0.0);
-    let focal_length = 1.0;
+    let vup = Vec3::new(0.0, 0.0, 1.0);
+    let vfov = 58.7;
     let aspect_ratio = 16.0 / 9.0;
     let image_width = 400;
-    let viewport_width = 2.0;
-    let u = Vec3::new(0.0, -1.0, 0.0);
     let background_color = Vec3::new(0.0, 0.0, 0.0);
 
     let red_cloth= material::diffusive::Diffusive::new(Vec3::new(0.7, 0.3, 0.3));
@@ -101,29 +305,91 @@ fn main() {
             Box::new(light_ball),
             Box::new(light_quad),
             ],
+        // the silver spheres share one material and the lights another
+        material_ids: vec![0, 1, 1, 0, 2, 2],
+        fog: None,
     };
 
     for i in 0..30 {
         for j in 0..30{
-            world.add_hittable(hittable::sphere::Sphere::new(Vec3::new(-15.0 + i as f64, -15.0 + j as f64, 0.5), 0.1, silver_metal.clone()));
+            world.add_hittable_with_material(hittable::sphere::Sphere::new(Vec3::new(-15.0 + i as f64, -15.0 + j as f64, 0.5), 0.1, silver_metal.clone()), 1);
         }
     }
 
-    let camera = Camera::new(
-        center,
-        look_to,
-        focal_length,
-        aspect_ratio,
-        image_width,
-        viewport_width,
-        u,
-        world,
-        background_color,
-    );
+    let mut camera_builder = CameraBuilder::new()
+        .lookfrom(center)
+        .lookat(look_to)
+        .vup(vup)
+        .vfov(vfov)
+        .aspect_ratio(aspect_ratio);
 
-    let picture: RgbImage = render_multi_thread(camera, 32, 4);
+    let mut animation = None;
+    if let Some(scene) = scene {
+        world = scene.world;
+        if let Some(builder) = scene.camera {
+            camera_builder = builder;
+        }
+        animation = scene.animation;
+    }
+
+    if let Some(rig) = stereo {
+        rig.check_projection(camera_builder.projection_kind()).unwrap_or_else(|e| panic!("{}", e));
+    }
+    let camera = camera_builder
+        .image_width(image_width)
+        .background_color(background_color)
+        .build(world)
+        .unwrap_or_else(|e| panic!("{}", e));
+    let mut camera = Arc::new(camera);
+
+    // animated scenes are written as a numbered sequence per output instead
+    if let Some(animation) = animation {
+        animation::render_sequence(&mut camera, &animation, &outputs, &settings.tone_mapping, |camera| {
+            settings.post.apply(&render_progressive(camera, &settings).to_hdr())
+        })
+        .unwrap_or_else(|e| panic!("{}", e));
+        println!("Take {:?} to render!", start.elapsed());
+        return;
+    }
+
+    // both eyes packed into one image
+    if let Some(rig) = stereo {
+        let picture = stereo::render_stereo(&mut camera, rig, |camera| {
+            settings.post.apply(&render_progressive(camera, &settings).to_hdr())
+        });
+        println!("Take {:?} to render!", start.elapsed());
+        for path in &outputs {
+            picture.save(path, &settings.tone_mapping).unwrap_or_else(|e| panic!("{}", e));
+        }
+        return;
+    }
+
+    let film = match resume_path {
+        Some(path) => {
+            let checkpoint = Checkpoint::load(&path).unwrap_or_else(|e| panic!("{}", e));
+            checkpoint.apply_settings(&mut settings);
+            resume_progressive(&camera, &settings, checkpoint).unwrap_or_else(|e| panic!("{}", e))
+        }
+        None => render_progressive(&camera, &settings),
+    };
     // let picture: RgbImage = camera.render();
     let duration = start.elapsed();
     println!("Take {:?} to render!", duration);
-    picture.save("output/test3.png").unwrap();
+    // the denoiser needs the AOVs as well
+    let aovs = if aov_path.is_some() || denoiser.is_some() {
+        Some(aov::render_aovs(&camera, &settings))
+    } else {
+        None
+    };
+    let picture = match (&denoiser, &aovs) {
+        (Some(denoiser), Some(aovs)) => denoiser.denoise(&film, aovs),
+        _ => film.to_hdr(),
+    };
+    let picture = settings.post.apply(&picture);
+    for path in &outputs {
+        picture.save(path, &settings.tone_mapping).unwrap_or_else(|e| panic!("{}", e));
+    }
+    if let (Some(path), Some(aovs)) = (aov_path, aovs) {
+        aovs.save(&path, &picture).unwrap_or_else(|e| panic!("{}", e));
+    }
 }
@@ -2,13 +2,19 @@ pub mod diffusive;
 pub mod metal;
 pub mod dieletric; 
 pub mod light;
+pub mod principled;
+pub mod mix;
+pub mod coated;
+pub mod isotropic;
+pub mod henyey_greenstein;
 
 use crate::util::ray::Ray;
+use crate::util::sampler::Sampler;
 use crate::hittable::HitRecord;
 use crate::util::vec3::Color;
 
 pub trait Material: Send + Sync {
-    fn scatter(&self, ray: &Ray, hit_record: &HitRecord) -> Ray {
+    fn scatter(&self, ray: &Ray, hit_record: &HitRecord, _sampler: &mut dyn Sampler) -> Ray {
         Ray::new(hit_record.point, ray.dir)
     }
 
@@ -19,4 +25,52 @@ pub trait Material: Send + Sync {
     fn is_light(&self) -> bool {
         false
     }
-}
\ No newline at end of file
+
+    // surface color at the hit point for the albedo AOV, textured materials override this
+    fn albedo(&self, _hit_record: &HitRecord) -> Color {
+        self.attenuation()
+    }
+
+    // radiance emitted at the hit point, lights stop the path while
+    // other materials may emit and scatter at the same time, e.g. fire
+    fn emitted(&self, _hit_record: &HitRecord) -> Color {
+        if self.is_light() {
+            self.attenuation()
+        } else {
+            Color::new(0.0, 0.0, 0.0)
+        }
+    }
+
+    // scatter the ray together with the attenuation it carries,
+    // materials whose attenuation depends on the sampled direction override this
+    fn scatter_with_attenuation(&self, ray: &Ray, hit_record: &HitRecord, sampler: &mut dyn Sampler) -> (Ray, Color) {
+        (self.scatter(ray, hit_record, sampler), self.attenuation())
+    }
+}
+
+// allow materials chosen at runtime, e.g. by the scene loader
+impl<T: Material + ?Sized> Material for Box<T> {
+    fn scatter(&self, ray: &Ray, hit_record: &HitRecord, sampler: &mut dyn Sampler) -> Ray {
+        (**self).scatter(ray, hit_record, sampler)
+    }
+
+    fn attenuation(&self) -> Color {
+        (**self).attenuation()
+    }
+
+    fn is_light(&self) -> bool {
+        (**self).is_light()
+    }
+
+    fn albedo(&self, hit_record: &HitRecord) -> Color {
+        (**self).albedo(hit_record)
+    }
+
+    fn emitted(&self, hit_record: &HitRecord) -> Color {
+        (**self).emitted(hit_record)
+    }
+
+    fn scatter_with_attenuation(&self, ray: &Ray, hit_record: &HitRecord, sampler: &mut dyn Sampler) -> (Ray, Color) {
+        (**self).scatter_with_attenuation(ray, hit_record, sampler)
+    }
+}
@@ -0,0 +1,96 @@
+use crate::util::ray::Ray;
+use crate::util::vec3::{Vec3, Color};
+use crate::util::sampler::Sampler;
+
+use super::Material;
+use super::dieletric::Dieletric;
+use super::metal::Metal;
+use crate::hittable::HitRecord;
+
+// Thin clear dielectric layer over any base material, e.g. varnish or car paint.
+// The coat reflects like a white metal with the fresnel probability of the layer,
+// the remaining light reaches the base and is tinted by the layer on its way.
+#[derive(Clone, Copy)]
+pub struct Coated<M: Material> {
+    pub base: M,
+    pub coat: Metal,
+    pub ita: f64,
+    pub tint: Color,
+}
+
+impl<M: Material> Coated<M> {
+    pub fn new(base: M, ita: f64, roughness: f64) -> Self {
+        Self {
+            base,
+            coat: Metal::new(Color::ones(), roughness),
+            ita,
+            tint: Color::ones(),
+        }
+    }
+}
+
+impl<M: Material> Material for Coated<M> {
+    fn scatter(&self, ray: &Ray, hit_record: &HitRecord, sampler: &mut dyn Sampler) -> Ray {
+        self.scatter_with_attenuation(ray, hit_record, sampler).0
+    }
+
+    fn attenuation(&self) -> Color {
+        self.base.attenuation() * self.tint
+    }
+
+    fn albedo(&self, hit_record: &HitRecord) -> Color {
+        self.base.albedo(hit_record) * self.tint
+    }
+
+    // an emitting base shines through the coat, which still reflects like over any other base
+    fn emitted(&self, hit_record: &HitRecord) -> Color {
+        self.base.emitted(hit_record) * self.tint
+    }
+
+    fn scatter_with_attenuation(&self, ray: &Ray, hit_record: &HitRecord, sampler: &mut dyn Sampler) -> (Ray, Color) {
+        let cos_theta = (-Vec3::dot(&ray.dir, &hit_record.normal)).max(0.0).min(1.0);
+        if Dieletric::reflectance(cos_theta, 1.0 / self.ita) > sampler.next_f64() {
+            return self.coat.scatter_with_attenuation(ray, hit_record, sampler);
+        }
+        let (scattered_ray, attenuation) = self.base.scatter_with_attenuation(ray, hit_record, sampler);
+        (scattered_ray, attenuation * self.tint)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::diffusive::Diffusive;
+    use crate::material::light::Light;
+    use crate::util::sampler::Independent;
+    use crate::util::vec3::Point3;
+
+    #[test]
+    fn test_coat_over_light_and_diffuse() {
+        let tint = Color::new(1.0, 0.5, 0.5);
+        let lamp = Coated { tint, ..Coated::new(Light::new(Color::ones() * 4.0), 1.5, 0.0) };
+        let hit_record = HitRecord::new(Point3::zero(), 1.0, Vec3::new(0.0, 0.0, 1.0), true, &lamp);
+        assert!(!lamp.is_light());
+        assert_eq!(lamp.emitted(&hit_record), Color::new(4.0, 2.0, 2.0));
+
+        // head on, the coat reflects 4% like a mirror, the rest reaches the light and ends there
+        let ray = Ray::new(Point3::new(0.0, 0.0, 1.0), Vec3::new(0.0, 0.0, -1.0));
+        let mut sampler = Independent::new(1);
+        let n = 10000;
+        let mut reflected = 0;
+        for _ in 0..n {
+            let (scattered, attenuation) = lamp.scatter_with_attenuation(&ray, &hit_record, &mut sampler);
+            if attenuation.near_zero() {
+                continue;
+            }
+            assert_eq!(attenuation, Color::ones());
+            assert!(scattered.dir.z > 0.99);
+            reflected += 1;
+        }
+        assert!((reflected as f64 / n as f64 - 0.04).abs() < 0.01);
+
+        let paint = Coated { tint, ..Coated::new(Diffusive::new(Color::ones() * 0.5), 1.5, 0.0) };
+        assert_eq!(paint.albedo(&hit_record), Color::new(0.5, 0.25, 0.25));
+        assert_eq!(paint.emitted(&hit_record), Color::zero());
+    }
+}
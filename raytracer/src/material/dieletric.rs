@@ -1,19 +1,78 @@
 use crate::util::ray::Ray;
 use crate::util::vec3::{Vec3, Color};
-use rand::Rng;
+use crate::util::sampler::Sampler;
 
 use super::Material;
 use crate::hittable::HitRecord;
 
+// wavelength dependent index of refraction, wavelengths are given in nanometers
+#[derive(Clone, Copy, Debug)]
+pub enum Dispersion {
+    // n = a + b / lambda^2, with lambda in micrometers
+    Cauchy { a: f64, b: f64 },
+    // n^2 = 1 + sum(b_i * lambda^2 / (lambda^2 - c_i)), with lambda in micrometers
+    Sellmeier { b: [f64; 3], c: [f64; 3] },
+}
+
+impl Dispersion {
+    // Schott N-BK7 crown glass
+    pub fn bk7() -> Self {
+        Dispersion::Sellmeier {
+            b: [1.03961212, 0.231792344, 1.01046945],
+            c: [0.00600069867, 0.0200179144, 103.560653],
+        }
+    }
+
+    // Schott SF11 dense flint glass, much stronger dispersion than BK7
+    pub fn sf11() -> Self {
+        Dispersion::Sellmeier {
+            b: [1.73759695, 0.313747346, 1.89878101],
+            c: [0.013188707, 0.0623068142, 155.23629],
+        }
+    }
+
+    pub fn ior(&self, wavelength: f64) -> f64 {
+        let l = wavelength * 1e-3;
+        let l2 = l * l;
+        match self {
+            Dispersion::Cauchy { a, b } => a + b / l2,
+            Dispersion::Sellmeier { b, c } => {
+                let mut n2 = 1.0;
+                for i in 0..3 {
+                    n2 += b[i] * l2 / (l2 - c[i]);
+                }
+                n2.sqrt()
+            }
+        }
+    }
+}
+
+// wavelength of the sodium D line, where the nominal index of refraction is measured
+const SODIUM_D_WAVELENGTH: f64 = 589.3;
+
 #[derive(Clone, Copy)]
 pub struct Dieletric {
     pub albedo: Color,
     pub ita: f64,
+    pub dispersion: Option<Dispersion>,
 }
 
 impl Dieletric {
     pub fn new(albedo: Color, ita: f64) -> Self {
-        Self { albedo, ita }
+        Self { albedo, ita, dispersion: None }
+    }
+
+    // ita is taken at the sodium D line and used when rendering without wavelengths
+    pub fn new_dispersive(albedo: Color, dispersion: Dispersion) -> Self {
+        let ita = dispersion.ior(SODIUM_D_WAVELENGTH);
+        Self { albedo, ita, dispersion: Some(dispersion) }
+    }
+
+    fn ita_at(&self, wavelength: Option<f64>) -> f64 {
+        match (wavelength, &self.dispersion) {
+            (Some(wavelength), Some(dispersion)) => dispersion.ior(wavelength),
+            _ => self.ita,
+        }
     }
 
     fn reflect(&self, ray: &Ray, hit_record: &HitRecord) -> Vec3 {
@@ -34,7 +93,8 @@ impl Dieletric {
 
     }
 
-    fn reflectance(cos_theta: f64, refraction_ratio: f64) -> f64 {
+    // schlick approximation of the fresnel reflectance
+    pub fn reflectance(cos_theta: f64, refraction_ratio: f64) -> f64 {
         let r0 = (1.0 - refraction_ratio) / (1.0 + refraction_ratio);
         let r0 = r0 * r0;
         r0 + (1.0 - r0) * (1.0 - cos_theta).powi(5)
@@ -43,19 +103,18 @@ impl Dieletric {
 }
 
 impl Material for Dieletric {
-    fn scatter(&self, ray: &Ray, hit_record: &HitRecord) -> Ray {
+    fn scatter(&self, ray: &Ray, hit_record: &HitRecord, sampler: &mut dyn Sampler) -> Ray {
         let cos_theta = -Vec3::dot(&ray.dir, &hit_record.normal);
         let sin_theta = (1.0 - cos_theta * cos_theta).sqrt();
         
+        let ita = self.ita_at(ray.wavelength);
         let refraction_ratio = if hit_record.is_outward {
-            1.0 / self.ita
+            1.0 / ita
         } else {
-            self.ita
+            ita
         };
 
-        let mut rng  = rand::thread_rng();
-
-        let cannot_refract = refraction_ratio * sin_theta > 1.0 || Self::reflectance(cos_theta, refraction_ratio) > rng.gen();
+        let cannot_refract = refraction_ratio * sin_theta > 1.0 || Self::reflectance(cos_theta, refraction_ratio) > sampler.next_f64();
 
         let direction = if cannot_refract {
             self.reflect(&ray, &hit_record)
@@ -69,4 +128,33 @@ impl Material for Dieletric {
     fn attenuation(&self) -> Color {
         self.albedo
     }
-}
\ No newline at end of file
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::light::Light;
+    use crate::util::vec3::Point3;
+
+    #[test]
+    fn test_dispersion_splits_wavelengths() {
+        let glass = Dieletric::new_dispersive(Color::ones(), Dispersion::sf11());
+        assert!((glass.ita - 1.7847).abs() < 1e-3);
+        let light = Light::new(Color::ones());
+        let hit_record = HitRecord::new(Point3::zero(), 1.0, Vec3::new(0.0, 0.0, 1.0), true, &light);
+        let ray = Ray::new(Point3::new(-1.0, 0.0, 1.0), Vec3::new(1.0, 0.0, -1.0).unit());
+
+        // angle to the inward normal after entering the glass
+        let refracted = |wavelength: Option<f64>| {
+            let direction = glass.refract(&ray, &hit_record, 1.0 / glass.ita_at(wavelength));
+            Vec3::dot(&direction, &Vec3::new(0.0, 0.0, -1.0)).acos()
+        };
+        let (blue, red) = (refracted(Some(450.0)), refracted(Some(650.0)));
+        // blue has the higher index and bends more towards the normal
+        assert!(red - blue > 0.005, "{} vs {}", blue, red);
+        assert!((refracted(None) - refracted(Some(SODIUM_D_WAVELENGTH))).abs() < 1e-12);
+        // a plain glass does not care about the wavelength
+        let plain = Dieletric::new(Color::ones(), 1.5);
+        assert_eq!(plain.ita_at(Some(450.0)), plain.ita_at(Some(650.0)));
+    }
+}
@@ -1,4 +1,5 @@
 use crate::util::ray::Ray;
+use crate::util::sampler::Sampler;
 use crate::util::vec3::{Vec3, Color};
 
 use super::Material;
@@ -16,8 +17,8 @@ impl Diffusive {
 }
 
 impl Material for Diffusive {
-    fn scatter(&self, _ray: &Ray, hit_record: &HitRecord) -> Ray {
-        let mut scatter_direction = hit_record.normal + Vec3::random_unit();
+    fn scatter(&self, _ray: &Ray, hit_record: &HitRecord, sampler: &mut dyn Sampler) -> Ray {
+        let mut scatter_direction = hit_record.normal + Vec3::random_unit(sampler);
         if scatter_direction.near_zero() {
             scatter_direction = hit_record.normal;
         }
@@ -0,0 +1,48 @@
+use crate::util::onb::ONB;
+use crate::util::ray::Ray;
+use crate::util::vec3::Color;
+use crate::util::sampler::Sampler;
+use std::f64::consts::PI;
+
+use super::Material;
+use crate::hittable::HitRecord;
+
+// Anisotropic phase function of participating media. g in (-1, 1) is the mean
+// cosine of the scattering angle, positive values scatter forward.
+#[derive(Clone, Copy)]
+pub struct HenyeyGreenstein {
+    pub albedo: Color,
+    pub g: f64,
+}
+
+impl HenyeyGreenstein {
+    pub fn new(albedo: Color, g: f64) -> Self {
+        Self { albedo, g }
+    }
+
+    // invert the cdf of the phase function for the cosine of the scattering angle
+    fn sample_cos_theta(&self, u: f64) -> f64 {
+        if self.g.abs() < 1e-3 {
+            return 1.0 - 2.0 * u;
+        }
+        let g = self.g;
+        let s = (1.0 - g * g) / (1.0 - g + 2.0 * g * u);
+        ((1.0 + g * g - s * s) / (2.0 * g)).max(-1.0).min(1.0)
+    }
+}
+
+impl Material for HenyeyGreenstein {
+    fn scatter(&self, ray: &Ray, hit_record: &HitRecord, sampler: &mut dyn Sampler) -> Ray {
+        let (u1, u2) = sampler.next_2d();
+        let cos_theta = self.sample_cos_theta(u1);
+        let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+        let phi = 2.0 * PI * u2;
+        let onb = ONB::new_from_w(ray.dir);
+        let direction = onb.local(sin_theta * phi.cos(), sin_theta * phi.sin(), cos_theta);
+        Ray::new(hit_record.point, direction.unit())
+    }
+
+    fn attenuation(&self) -> Color {
+        self.albedo
+    }
+}
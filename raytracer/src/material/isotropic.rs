@@ -0,0 +1,32 @@
+use crate::util::ray::Ray;
+use crate::util::sampler::Sampler;
+use crate::util::vec3::{Vec3, Color};
+
+use super::Material;
+use crate::hittable::HitRecord;
+
+// phase function of participating media, scatters uniformly in all directions
+#[derive(Clone, Copy)]
+pub struct Isotropic {
+    pub albedo: Color,
+}
+
+impl Isotropic {
+    pub fn new(albedo: Color) -> Self {
+        Self { albedo }
+    }
+}
+
+impl Material for Isotropic {
+    fn scatter(&self, _ray: &Ray, hit_record: &HitRecord, sampler: &mut dyn Sampler) -> Ray {
+        let mut scatter_direction = Vec3::random_unit(sampler);
+        while scatter_direction.near_zero() {
+            scatter_direction = Vec3::random_unit(sampler);
+        }
+        Ray::new(hit_record.point, scatter_direction.unit())
+    }
+
+    fn attenuation(&self) -> Color {
+        self.albedo
+    }
+}
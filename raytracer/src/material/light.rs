@@ -1,4 +1,5 @@
 use crate::util::ray::Ray;
+use crate::util::sampler::Sampler;
 use crate::util::vec3::{Vec3, Color};
 
 use super::Material;
@@ -16,7 +17,7 @@ impl Light {
 }
 
 impl Material for Light {
-    fn scatter(&self, _ray: &Ray, hit_record: &HitRecord) -> Ray {
+    fn scatter(&self, _ray: &Ray, hit_record: &HitRecord, _sampler: &mut dyn Sampler) -> Ray {
         Ray::new(hit_record.point, Vec3::new(0.0, 0.0, 0.0))
     }
 
@@ -27,4 +28,10 @@ impl Material for Light {
     fn is_light(&self) -> bool {
         true
     }
+
+    // Lights end the path. This matters when a light is part of another material, e.g.
+    // one side of a Mix, which keeps scattering and would otherwise trace on from here.
+    fn scatter_with_attenuation(&self, _ray: &Ray, hit_record: &HitRecord, _sampler: &mut dyn Sampler) -> (Ray, Color) {
+        (Ray::new(hit_record.point, hit_record.normal), Color::zero())
+    }
 }
\ No newline at end of file
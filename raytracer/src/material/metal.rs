@@ -1,4 +1,5 @@
 use crate::util::ray::Ray;
+use crate::util::sampler::Sampler;
 use crate::util::vec3::{Vec3, Color};
 
 use super::Material;
@@ -17,9 +18,9 @@ impl Metal {
 }
 
 impl Material for Metal {
-    fn scatter(&self, ray: &Ray, hit_record: &HitRecord) -> Ray {
+    fn scatter(&self, ray: &Ray, hit_record: &HitRecord, sampler: &mut dyn Sampler) -> Ray {
         let scatter_direction_reflect = ray.dir - hit_record.normal * 2.0 * Vec3::dot(&ray.dir, &hit_record.normal);
-        let scatter_direction = scatter_direction_reflect + Vec3::random_unit() * self.fuzz;
+        let scatter_direction = scatter_direction_reflect + Vec3::random_unit(sampler) * self.fuzz;
         if scatter_direction.near_zero() {
             return Ray::new(hit_record.point, hit_record.normal);
         }
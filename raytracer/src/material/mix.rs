@@ -0,0 +1,112 @@
+use crate::util::ray::Ray;
+use crate::util::vec3::Color;
+use crate::texture::Texture;
+use crate::texture::constant::ConstantTexture;
+use crate::util::sampler::Sampler;
+
+use super::Material;
+use crate::hittable::HitRecord;
+
+// Stochastic blend of two materials: `b` is picked with the probability given
+// by the weight texture at the hit point and `a` otherwise.
+#[derive(Clone, Copy)]
+pub struct Mix<A: Material, B: Material, W: Texture> {
+    pub a: A,
+    pub b: B,
+    pub weight: W,
+}
+
+impl<A: Material, B: Material> Mix<A, B, ConstantTexture> {
+    pub fn new(a: A, b: B, weight: f64) -> Self {
+        Self::new_textured(a, b, ConstantTexture::new(Color::ones() * weight))
+    }
+}
+
+impl<A: Material, B: Material, W: Texture> Mix<A, B, W> {
+    pub fn new_textured(a: A, b: B, weight: W) -> Self {
+        Self { a, b, weight }
+    }
+
+    fn weight_at(&self, hit_record: &HitRecord) -> f64 {
+        let w = self.weight.value(&hit_record.point);
+        ((w.x + w.y + w.z) / 3.0).max(0.0).min(1.0)
+    }
+}
+
+impl<A: Material, B: Material, W: Texture> Material for Mix<A, B, W> {
+    fn scatter(&self, ray: &Ray, hit_record: &HitRecord, sampler: &mut dyn Sampler) -> Ray {
+        self.scatter_with_attenuation(ray, hit_record, sampler).0
+    }
+
+    // the attenuation depends on the hit point, see scatter_with_attenuation
+
+    fn is_light(&self) -> bool {
+        self.a.is_light() && self.b.is_light()
+    }
+
+    fn emitted(&self, hit_record: &HitRecord) -> Color {
+        let w = self.weight_at(hit_record);
+        self.a.emitted(hit_record) * (1.0 - w) + self.b.emitted(hit_record) * w
+    }
+
+    fn albedo(&self, hit_record: &HitRecord) -> Color {
+        let w = self.weight_at(hit_record);
+        self.a.albedo(hit_record) * (1.0 - w) + self.b.albedo(hit_record) * w
+    }
+
+    fn scatter_with_attenuation(&self, ray: &Ray, hit_record: &HitRecord, sampler: &mut dyn Sampler) -> (Ray, Color) {
+        if sampler.next_f64() < self.weight_at(hit_record) {
+            self.b.scatter_with_attenuation(ray, hit_record, sampler)
+        } else {
+            self.a.scatter_with_attenuation(ray, hit_record, sampler)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::diffusive::Diffusive;
+    use crate::material::light::Light;
+    use crate::texture::checker::CheckerTexture;
+    use crate::util::sampler::Independent;
+    use crate::util::vec3::{Point3, Vec3};
+
+    #[test]
+    fn test_mix_with_light() {
+        let mix = Mix::new(Diffusive::new(Color::ones() * 0.5), Light::new(Color::ones() * 4.0), 0.25);
+        let hit_record = HitRecord::new(Point3::zero(), 1.0, Vec3::new(0.0, 0.0, 1.0), true, &mix);
+        assert!(!mix.is_light());
+        assert_eq!(mix.emitted(&hit_record), Color::ones());
+        assert_eq!(mix.albedo(&hit_record), Color::ones() * 1.375);
+
+        // the light side ends the path instead of scattering with the light color
+        let ray = Ray::new(Point3::new(0.0, 0.0, 1.0), Vec3::new(0.0, 0.0, -1.0));
+        let mut sampler = Independent::new(2);
+        let n = 10000;
+        let mut ended = 0;
+        for _ in 0..n {
+            let (scattered, attenuation) = mix.scatter_with_attenuation(&ray, &hit_record, &mut sampler);
+            if attenuation.near_zero() {
+                ended += 1;
+            } else {
+                assert_eq!(attenuation, Color::ones() * 0.5);
+                assert!(scattered.dir.z > 0.0);
+            }
+        }
+        assert!((ended as f64 / n as f64 - 0.25).abs() < 0.02);
+
+        let both = Mix::new(Light::new(Color::ones()), Light::new(Color::ones() * 3.0), 0.5);
+        assert!(both.is_light());
+        assert_eq!(both.emitted(&hit_record), Color::ones() * 2.0);
+    }
+
+    #[test]
+    fn test_textured_weight() {
+        let checker = CheckerTexture::new(ConstantTexture::new(Color::zero()), ConstantTexture::new(Color::ones()), 1.0);
+        let mix = Mix::new_textured(Diffusive::new(Color::new(1.0, 0.0, 0.0)), Diffusive::new(Color::new(0.0, 0.0, 1.0)), checker);
+        let at = |x: f64| HitRecord::new(Point3::new(x, 1.0, 1.0), 1.0, Vec3::new(0.0, 0.0, 1.0), true, &mix);
+        assert_eq!(mix.albedo(&at(1.0)), Color::new(1.0, 0.0, 0.0));
+        assert_eq!(mix.albedo(&at(-1.0)), Color::new(0.0, 0.0, 1.0));
+    }
+}
@@ -0,0 +1,288 @@
+use crate::util::onb::ONB;
+use crate::util::ray::Ray;
+use crate::util::vec3::{Vec3, Color};
+use crate::util::sampler::Sampler;
+use std::f64::consts::PI;
+
+use super::Material;
+use crate::hittable::HitRecord;
+
+// Principled uber material after Burley 2012. A single lobe (diffuse, specular,
+// clearcoat or transmission) is picked per scattering event and the returned
+// attenuation is divided by the probability of picking it.
+#[derive(Clone, Copy)]
+pub struct Principled {
+    pub base_color: Color,
+    pub metallic: f64,
+    pub roughness: f64,
+    pub specular: f64,
+    pub specular_tint: f64,
+    pub sheen: f64,
+    pub sheen_tint: f64,
+    pub clearcoat: f64,
+    pub clearcoat_gloss: f64,
+    pub transmission: f64,
+    pub ior: f64,
+    pub subsurface: f64,
+}
+
+impl Principled {
+    pub fn new(base_color: Color) -> Self {
+        Self {
+            base_color,
+            metallic: 0.0,
+            roughness: 0.5,
+            specular: 0.5,
+            specular_tint: 0.0,
+            sheen: 0.0,
+            sheen_tint: 0.5,
+            clearcoat: 0.0,
+            clearcoat_gloss: 1.0,
+            transmission: 0.0,
+            ior: 1.5,
+            subsurface: 0.0,
+        }
+    }
+
+    fn luminance(color: Color) -> f64 {
+        0.2126 * color.x + 0.7152 * color.y + 0.0722 * color.z
+    }
+
+    fn lerp(a: Color, b: Color, t: f64) -> Color {
+        a * (1.0 - t) + b * t
+    }
+
+    fn schlick_weight(cos_theta: f64) -> f64 {
+        (1.0 - cos_theta.max(0.0)).powi(5)
+    }
+
+    fn schlick(f0: Color, cos_theta: f64) -> Color {
+        Self::lerp(f0, Color::ones(), Self::schlick_weight(cos_theta))
+    }
+
+    // exact fresnel reflectance of a dielectric, eta is the ratio of the indices of refraction
+    fn fresnel_dielectric(cos_i: f64, eta: f64) -> f64 {
+        let sin2_t = eta * eta * (1.0 - cos_i * cos_i);
+        if sin2_t >= 1.0 {
+            return 1.0;
+        }
+        let cos_t = (1.0 - sin2_t).sqrt();
+        let r_s = (eta * cos_i - cos_t) / (eta * cos_i + cos_t);
+        let r_p = (cos_i - eta * cos_t) / (cos_i + eta * cos_t);
+        0.5 * (r_s * r_s + r_p * r_p)
+    }
+
+    // hue and saturation of the base color with unit luminance
+    fn tint(&self) -> Color {
+        let luminance = Self::luminance(self.base_color);
+        if luminance > 0.0 {
+            self.base_color / luminance
+        } else {
+            Color::ones()
+        }
+    }
+
+    fn specular_f0(&self) -> Color {
+        let dielectric = Self::lerp(Color::ones(), self.tint(), self.specular_tint) * (0.08 * self.specular);
+        Self::lerp(dielectric, self.base_color, self.metallic)
+    }
+
+    // smith masking term for GGX
+    fn smith_g1(cos_theta: f64, alpha: f64) -> f64 {
+        let a2 = alpha * alpha;
+        2.0 * cos_theta / (cos_theta + (a2 + (1.0 - a2) * cos_theta * cos_theta).sqrt())
+    }
+
+    // sample a microfacet normal from the GGX distribution
+    fn sample_ggx(onb: &ONB, alpha: f64, sampler: &mut dyn Sampler) -> Vec3 {
+        let (u1, u2) = sampler.next_2d();
+        let phi = 2.0 * PI * u1;
+        let cos_theta = ((1.0 - u2) / (1.0 + (alpha * alpha - 1.0) * u2)).sqrt();
+        let sin_theta = (1.0 - cos_theta * cos_theta).max(0.0).sqrt();
+        onb.local(sin_theta * phi.cos(), sin_theta * phi.sin(), cos_theta)
+    }
+
+    fn sample_cosine(onb: &ONB, sampler: &mut dyn Sampler) -> Vec3 {
+        let (u1, u2) = sampler.next_2d();
+        let phi = 2.0 * PI * u1;
+        let r = u2.sqrt();
+        onb.local(r * phi.cos(), r * phi.sin(), (1.0 - u2).sqrt())
+    }
+
+    fn reflect(v: Vec3, h: Vec3) -> Vec3 {
+        h * (2.0 * Vec3::dot(&v, &h)) - v
+    }
+
+    // returns the reflected direction and f * cos / pdf of a GGX reflection lobe
+    fn sample_microfacet(onb: &ONB, v: Vec3, alpha: f64, f0: Color, sampler: &mut dyn Sampler) -> (Vec3, Color) {
+        let h = Self::sample_ggx(onb, alpha, sampler);
+        let l = Self::reflect(v, h);
+        let n_dot_l = Vec3::dot(&onb.w, &l);
+        let n_dot_v = Vec3::dot(&onb.w, &v).max(1e-6);
+        let n_dot_h = Vec3::dot(&onb.w, &h).max(1e-6);
+        let v_dot_h = Vec3::dot(&v, &h);
+        if n_dot_l <= 0.0 || v_dot_h <= 0.0 {
+            return (l, Color::zero());
+        }
+        let g = Self::smith_g1(n_dot_v, alpha) * Self::smith_g1(n_dot_l, alpha);
+        let f = Self::schlick(f0, v_dot_h);
+        (l, f * (g * v_dot_h / (n_dot_v * n_dot_h)))
+    }
+
+    fn sample_diffuse(&self, onb: &ONB, v: Vec3, sampler: &mut dyn Sampler) -> (Vec3, Color) {
+        let l = Self::sample_cosine(onb, sampler);
+        let n_dot_l = Vec3::dot(&onb.w, &l).max(1e-6);
+        let n_dot_v = Vec3::dot(&onb.w, &v).max(1e-6);
+        let half = l + v;
+        let cos_d = if half.near_zero() {
+            1.0
+        } else {
+            Vec3::dot(&l, &half.unit())
+        };
+        let f_l = Self::schlick_weight(n_dot_l);
+        let f_v = Self::schlick_weight(n_dot_v);
+
+        // retro-reflection at grazing angles
+        let fd90 = 0.5 + 2.0 * self.roughness * cos_d * cos_d;
+        let fd = (1.0 + (fd90 - 1.0) * f_l) * (1.0 + (fd90 - 1.0) * f_v);
+        // Hanrahan-Krueger like flattening for subsurface scattering
+        let fss90 = self.roughness * cos_d * cos_d;
+        let fss = (1.0 + (fss90 - 1.0) * f_l) * (1.0 + (fss90 - 1.0) * f_v);
+        let ss = 1.25 * (fss * (1.0 / (n_dot_l + n_dot_v) - 0.5) + 0.5);
+        let diffuse = fd * (1.0 - self.subsurface) + ss * self.subsurface;
+
+        let sheen_color = Self::lerp(Color::ones(), self.tint(), self.sheen_tint);
+        let sheen = sheen_color * (self.sheen * Self::schlick_weight(cos_d) * PI);
+        (l, self.base_color * diffuse + sheen)
+    }
+
+    // Rough glass (Walter et al. 2007): reflection or refraction through a GGX microfacet,
+    // picked by its fresnel term, so both share the weight G * v.h / (n.v * n.h).
+    fn sample_transmission(&self, onb: &ONB, v: Vec3, is_outward: bool, sampler: &mut dyn Sampler) -> (Vec3, Color) {
+        let alpha = (self.roughness * self.roughness).max(1e-3);
+        let h = Self::sample_ggx(onb, alpha, sampler);
+        let eta = if is_outward { 1.0 / self.ior } else { self.ior };
+        let cos_i = Vec3::dot(&v, &h);
+        let n_dot_v = Vec3::dot(&onb.w, &v).max(1e-6);
+        let n_dot_h = Vec3::dot(&onb.w, &h).max(1e-6);
+        if cos_i <= 0.0 {
+            return (h, Color::zero());
+        }
+        let reflectance = Self::fresnel_dielectric(cos_i, eta);
+        let reflected = reflectance > sampler.next_f64();
+        let (l, color) = if reflected {
+            (Self::reflect(v, h), Color::ones())
+        } else {
+            let k = 1.0 - eta * eta * (1.0 - cos_i * cos_i);
+            ((-v * eta + h * (eta * cos_i - k.sqrt())).unit(), self.base_color)
+        };
+        // reflections have to leave above the surface and refractions below it
+        let n_dot_l = Vec3::dot(&onb.w, &l);
+        if n_dot_l == 0.0 || (n_dot_l > 0.0) != reflected {
+            return (l, Color::zero());
+        }
+        let g = Self::smith_g1(n_dot_v, alpha) * Self::smith_g1(n_dot_l.abs(), alpha);
+        (l, color * (g * cos_i / (n_dot_v * n_dot_h)))
+    }
+}
+
+impl Material for Principled {
+    fn scatter(&self, ray: &Ray, hit_record: &HitRecord, sampler: &mut dyn Sampler) -> Ray {
+        self.scatter_with_attenuation(ray, hit_record, sampler).0
+    }
+
+    fn attenuation(&self) -> Color {
+        self.base_color
+    }
+
+    fn scatter_with_attenuation(&self, ray: &Ray, hit_record: &HitRecord, sampler: &mut dyn Sampler) -> (Ray, Color) {
+        let onb = ONB::new_from_w(hit_record.normal);
+        let v = -ray.dir.unit();
+        let n_dot_v = Vec3::dot(&onb.w, &v).max(1e-6);
+
+        let f_spec = Self::luminance(Self::schlick(self.specular_f0(), n_dot_v)).min(1.0);
+        let diffuse_weight = (1.0 - self.metallic) * (1.0 - self.transmission) * (1.0 - f_spec);
+        // the specular lobe already reflects f_spec, like for the diffuse lobe
+        let transmission_weight = (1.0 - self.metallic) * self.transmission * (1.0 - f_spec);
+        let clearcoat_weight = 0.25 * self.clearcoat;
+
+        // lobe selection probabilities, roughly proportional to their albedo
+        let p_diffuse = diffuse_weight * Self::luminance(self.base_color).max(self.sheen).max(0.05);
+        let p_specular = f_spec.max(0.05);
+        let p_clearcoat = clearcoat_weight * Self::schlick_weight(n_dot_v).max(0.04);
+        let p_transmission = transmission_weight;
+        let total = p_diffuse + p_specular + p_clearcoat + p_transmission;
+
+        let choice = sampler.next_f64() * total;
+        let (direction, attenuation) = if choice < p_diffuse {
+            let (l, value) = self.sample_diffuse(&onb, v, sampler);
+            (l, value * (diffuse_weight * total / p_diffuse))
+        } else if choice < p_diffuse + p_specular {
+            let alpha = (self.roughness * self.roughness).max(1e-3);
+            let (l, value) = Self::sample_microfacet(&onb, v, alpha, self.specular_f0(), sampler);
+            (l, value * (total / p_specular))
+        } else if choice < p_diffuse + p_specular + p_clearcoat {
+            let alpha = 0.1 * (1.0 - self.clearcoat_gloss) + 0.001 * self.clearcoat_gloss;
+            let (l, value) = Self::sample_microfacet(&onb, v, alpha, Color::ones() * 0.04, sampler);
+            (l, value * (clearcoat_weight * total / p_clearcoat))
+        } else {
+            let (l, value) = self.sample_transmission(&onb, v, hit_record.is_outward, sampler);
+            (l, value * (transmission_weight * total / p_transmission))
+        };
+
+        if direction.near_zero() {
+            return (Ray::new(hit_record.point, hit_record.normal), Color::zero());
+        }
+        (Ray::new(hit_record.point, direction.unit()), attenuation)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::metal::Metal;
+    use crate::util::sampler::Independent;
+    use crate::util::vec3::Point3;
+
+    fn hit_record(material: &dyn Material) -> HitRecord<'_> {
+        HitRecord::new(Point3::zero(), 1.0, Vec3::new(0.0, 0.0, 1.0), true, material)
+    }
+
+    #[test]
+    fn test_smooth_metal_is_a_mirror() {
+        let gold = Color::new(0.9, 0.6, 0.2);
+        let principled = Principled { metallic: 1.0, roughness: 0.0, ..Principled::new(gold) };
+        let metal = Metal::new(gold, 0.0);
+        let ray = Ray::new(Point3::new(-1.0, 0.0, 1.0), Vec3::new(1.0, 0.0, -1.0).unit());
+        let mut sampler = Independent::new(3);
+        let mut sharp = 0;
+        for _ in 0..100 {
+            let (scattered, attenuation) = principled.scatter_with_attenuation(&ray, &hit_record(&principled), &mut sampler);
+            let mirror = metal.scatter(&ray, &hit_record(&metal), &mut sampler);
+            // GGX has long tails even when it is this narrow
+            if (scattered.dir - mirror.dir).length() < 0.01 {
+                assert!((attenuation - gold).length() < 0.01);
+                sharp += 1;
+            }
+        }
+        assert!(sharp >= 95, "{} of 100 reflections are sharp", sharp);
+    }
+
+    #[test]
+    fn test_no_energy_gain() {
+        let mut sampler = Independent::new(4);
+        for &(metallic, roughness, transmission) in &[(0.0, 0.5, 0.0), (1.0, 0.3, 0.0), (0.0, 0.1, 1.0), (0.5, 0.8, 0.0)] {
+            let white = Principled { metallic, roughness, transmission, ..Principled::new(Color::ones()) };
+            for &angle in &[0.1f64, 0.8, 1.3] {
+                let ray = Ray::new(Point3::zero(), Vec3::new(angle.sin(), 0.0, -angle.cos()));
+                let n = 20000;
+                let mut total = 0.0;
+                for _ in 0..n {
+                    total += white.scatter_with_attenuation(&ray, &hit_record(&white), &mut sampler).1.y;
+                }
+                let albedo = total / n as f64;
+                assert!(albedo < 1.05, "metallic {} roughness {} at {} reflects {}", metallic, roughness, angle, albedo);
+            }
+        }
+    }
+}
@@ -0,0 +1,267 @@
+// Photographic effects applied to the linear image before it is tone mapped, so they see the
+// real brightness of the lights instead of values clipped at 1:
+//   chromatic aberration, red and blue magnified slightly differently from the image center,
+//   bloom, light above a threshold scattered into a soft halo,
+//   glare, the same light smeared into the star of streaks an aperture makes,
+//   vignetting, the cos^4 falloff towards the corners.
+// All distances are in pixels.
+
+use std::f64::consts::PI;
+
+use crate::film::luminance;
+use crate::hdr::HdrImage;
+use crate::util::vec3::Color;
+
+// The widest halo blurs with 27 * radius taps to every side, larger radii are cut to this.
+pub const MAX_BLOOM_RADIUS: f64 = 32.0;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Bloom {
+    pub threshold: f64, // luminance above which light blooms
+    pub intensity: f64, // fraction of the light above the threshold that goes into the halo
+    pub radius: f64,    // standard deviation of the narrowest of the three halos, at most MAX_BLOOM_RADIUS
+}
+
+impl Bloom {
+    pub fn new(threshold: f64) -> Self {
+        Self { threshold, intensity: 0.5, radius: 4.0 }
+    }
+}
+
+// Every pixel gathers 3 * length taps along every streak, longer streaks are cut to this.
+pub const MAX_GLARE_LENGTH: f64 = 100.0;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Glare {
+    pub threshold: f64,
+    pub intensity: f64,
+    pub length: f64,  // streaks fade out over this many pixels, at most MAX_GLARE_LENGTH
+    pub points: u32,  // number of streaks in the star
+    pub rotation: f64, // angle of the first streak in degrees, counterclockwise from the right
+}
+
+impl Glare {
+    pub fn new(threshold: f64) -> Self {
+        Self { threshold, intensity: 0.2, length: 60.0, points: 6, rotation: 15.0 }
+    }
+}
+
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PostEffects {
+    pub bloom: Option<Bloom>,
+    pub glare: Option<Glare>,
+    pub vignette: f64,             // 0 for none, 1 is the falloff of a lens about as long as the image diagonal
+    pub chromatic_aberration: f64, // relative magnification of red against blue, e.g. 0.005
+}
+
+// The image as linear colors row by row, with edge clamped bilinear lookups.
+struct Plane {
+    width: usize,
+    height: usize,
+    pixels: Vec<Color>,
+}
+
+impl Plane {
+    fn from_image(image: &HdrImage) -> Self {
+        let pixels = (0..image.width * image.height).map(|k| image.get(k % image.width, k / image.width)).collect();
+        Self { width: image.width as usize, height: image.height as usize, pixels }
+    }
+
+    fn get(&self, x: i64, y: i64) -> Color {
+        let x = x.max(0).min(self.width as i64 - 1) as usize;
+        let y = y.max(0).min(self.height as i64 - 1) as usize;
+        self.pixels[y * self.width + x]
+    }
+
+    // at a continuous position, pixel centers are at integer + 0.5
+    fn sample(&self, x: f64, y: f64) -> Color {
+        let (x, y) = (x - 0.5, y - 0.5);
+        let (x0, y0) = (x.floor(), y.floor());
+        let (tx, ty) = (x - x0, y - y0);
+        let (x0, y0) = (x0 as i64, y0 as i64);
+        let top = self.get(x0, y0) * (1.0 - tx) + self.get(x0 + 1, y0) * tx;
+        let bottom = self.get(x0, y0 + 1) * (1.0 - tx) + self.get(x0 + 1, y0 + 1) * tx;
+        top * (1.0 - ty) + bottom * ty
+    }
+
+    // the part of every pixel above the threshold, scaled as a whole so the hue stays
+    fn bright_pass(&self, threshold: f64) -> Plane {
+        let pixels = self
+            .pixels
+            .iter()
+            .map(|&color| {
+                let l = luminance(color);
+                if l > threshold {
+                    color * ((l - threshold) / l)
+                } else {
+                    Color::zero()
+                }
+            })
+            .collect();
+        Plane { width: self.width, height: self.height, pixels }
+    }
+
+    // separable gaussian, light blurred past the border is lost like it would be on a sensor
+    fn blur(&self, sigma: f64) -> Plane {
+        let reach = (3.0 * sigma).ceil() as i64;
+        let kernel: Vec<f64> = (-reach..=reach).map(|d| (-(d * d) as f64 / (2.0 * sigma * sigma)).exp()).collect();
+        let total: f64 = kernel.iter().sum();
+        let kernel: Vec<f64> = kernel.iter().map(|w| w / total).collect();
+        let (width, height) = (self.width as i64, self.height as i64);
+        let pass = |from: &[Color], dx: i64, dy: i64| -> Vec<Color> {
+            let mut to = vec![Color::zero(); from.len()];
+            for y in 0..height {
+                for x in 0..width {
+                    let mut sum = Color::zero();
+                    for (k, w) in kernel.iter().enumerate() {
+                        let d = k as i64 - reach;
+                        let (qx, qy) = (x + d * dx, y + d * dy);
+                        if qx >= 0 && qy >= 0 && qx < width && qy < height {
+                            sum += from[(qy * width + qx) as usize] * *w;
+                        }
+                    }
+                    to[(y * width + x) as usize] = sum;
+                }
+            }
+            to
+        };
+        let pixels = pass(&pass(&self.pixels, 1, 0), 0, 1);
+        Plane { width: self.width, height: self.height, pixels }
+    }
+}
+
+impl PostEffects {
+    pub fn is_none(&self) -> bool {
+        *self == PostEffects::default()
+    }
+
+    pub fn apply(&self, image: &HdrImage) -> HdrImage {
+        if self.is_none() {
+            return image.clone();
+        }
+        let mut plane = Plane::from_image(image);
+        let (width, height) = (image.width as f64, image.height as f64);
+        let (cx, cy) = (width / 2.0, height / 2.0);
+
+        if self.chromatic_aberration != 0.0 {
+            let scale = self.chromatic_aberration / 2.0;
+            let lens = Plane { width: plane.width, height: plane.height, pixels: plane.pixels.clone() };
+            for y in 0..plane.height {
+                for x in 0..plane.width {
+                    // red is spread out from the center, blue pulled in, green stays
+                    let (px, py) = (x as f64 + 0.5 - cx, y as f64 + 0.5 - cy);
+                    let red = lens.sample(cx + px / (1.0 + scale), cy + py / (1.0 + scale)).x;
+                    let blue = lens.sample(cx + px / (1.0 - scale), cy + py / (1.0 - scale)).z;
+                    let pixel = &mut plane.pixels[y * plane.width + x];
+                    pixel.x = red;
+                    pixel.z = blue;
+                }
+            }
+        }
+
+        let mut scattered = vec![Color::zero(); plane.pixels.len()];
+        if let Some(bloom) = self.bloom {
+            // a narrow core and wider tails, which a single gaussian does not have
+            let bright = plane.bright_pass(bloom.threshold);
+            let radius = bloom.radius.min(MAX_BLOOM_RADIUS);
+            for &(scale, weight) in &[(1.0, 0.5), (3.0, 0.3), (9.0, 0.2)] {
+                let halo = bright.blur(radius * scale);
+                for (s, h) in scattered.iter_mut().zip(&halo.pixels) {
+                    *s += *h * (weight * bloom.intensity);
+                }
+            }
+        }
+        if let Some(glare) = self.glare {
+            let bright = plane.bright_pass(glare.threshold);
+            let length = glare.length.min(MAX_GLARE_LENGTH);
+            let steps = (3.0 * length).ceil().max(1.0) as usize;
+            let falloff: Vec<f64> = (1..=steps).map(|s| (-(s as f64) / length).exp()).collect();
+            let total = falloff.iter().sum::<f64>() * glare.points as f64;
+            let directions: Vec<(f64, f64)> = (0..glare.points)
+                .map(|k| {
+                    let angle = glare.rotation.to_radians() + 2.0 * PI * k as f64 / glare.points as f64;
+                    // image rows go down
+                    (angle.cos(), -angle.sin())
+                })
+                .collect();
+            for y in 0..plane.height {
+                for x in 0..plane.width {
+                    let (px, py) = (x as f64 + 0.5, y as f64 + 0.5);
+                    let mut sum = Color::zero();
+                    for &(dx, dy) in &directions {
+                        // light a streak of length s away in the opposite direction ends up here
+                        for (s, w) in falloff.iter().enumerate() {
+                            let s = (s + 1) as f64;
+                            let (qx, qy) = (px - dx * s, py - dy * s);
+                            if qx < 0.0 || qy < 0.0 || qx >= width || qy >= height {
+                                break;
+                            }
+                            sum += bright.sample(qx, qy) * *w;
+                        }
+                    }
+                    scattered[y * plane.width + x] += sum * (glare.intensity / total);
+                }
+            }
+        }
+        for (p, s) in plane.pixels.iter_mut().zip(&scattered) {
+            *p += *s;
+        }
+
+        let half_diagonal = (cx * cx + cy * cy).sqrt();
+        HdrImage::from_fn(image.width, image.height, |i, j| {
+            let color = plane.pixels[j as usize * plane.width + i as usize];
+            if self.vignette == 0.0 {
+                return color;
+            }
+            let (px, py) = (i as f64 + 0.5 - cx, j as f64 + 0.5 - cy);
+            // cos^4 of the angle to the optical axis, tan of that angle is vignette * r
+            let tan = self.vignette * (px * px + py * py).sqrt() / half_diagonal;
+            color / (1.0 + tan * tan).powi(2)
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn spot() -> HdrImage {
+        HdrImage::from_fn(41, 41, |i, j| if (i, j) == (20, 20) { Color::ones() * 100.0 } else { Color::ones() * 0.1 })
+    }
+
+    #[test]
+    fn test_bloom_and_glare_spread_bright_light() {
+        let image = spot();
+        let bloomed = PostEffects { bloom: Some(Bloom::new(1.0)), ..PostEffects::default() }.apply(&image);
+        // the halo falls off with distance, dim pixels do not bloom themselves
+        assert!(bloomed.get(22, 20).x > bloomed.get(26, 20).x && bloomed.get(26, 20).x > 0.1 + 1e-3);
+        assert!((bloomed.get(0, 40).x - 0.1).abs() < 0.01);
+        // so is a halo wider than the maximum
+        let wide = |radius: f64| PostEffects { bloom: Some(Bloom { radius, ..Bloom::new(1.0) }), ..PostEffects::default() }.apply(&image);
+        assert_eq!(wide(1e300).get(0, 40), wide(MAX_BLOOM_RADIUS).get(0, 40));
+
+        let glare = Glare { points: 4, rotation: 0.0, ..Glare::new(1.0) };
+        let glared = PostEffects { glare: Some(glare), ..PostEffects::default() }.apply(&image);
+        // streaks along the axes, nothing on the diagonal
+        assert!(glared.get(30, 20).x > 0.15 && glared.get(20, 30).x > 0.15);
+        assert!((glared.get(27, 27).x - 0.1).abs() < 1e-6);
+        // longer streaks are cut to the maximum length
+        let long = |length: f64| PostEffects { glare: Some(Glare { length, ..glare }), ..PostEffects::default() }.apply(&image);
+        assert_eq!(long(1e6).get(30, 20), long(MAX_GLARE_LENGTH).get(30, 20));
+    }
+
+    #[test]
+    fn test_vignette_and_chromatic_aberration() {
+        let flat = HdrImage::from_fn(40, 30, |_, _| Color::ones());
+        let vignetted = PostEffects { vignette: 1.0, ..PostEffects::default() }.apply(&flat);
+        assert!(vignetted.get(20, 15).x > 0.99);
+        assert!((vignetted.get(0, 0).x - 0.25).abs() < 0.03);
+
+        // a white spot right of the center gets a red fringe outside and a blue one inside
+        let spot = HdrImage::from_fn(41, 41, |i, j| if (30..33).contains(&i) && (19..22).contains(&j) { Color::ones() } else { Color::zero() });
+        let shifted = PostEffects { chromatic_aberration: 0.2, ..PostEffects::default() }.apply(&spot);
+        assert!(shifted.get(33, 20).x > shifted.get(33, 20).z);
+        assert!(shifted.get(29, 20).z > shifted.get(29, 20).x);
+        assert_eq!(PostEffects::default().apply(&spot).get(31, 20), Color::ones());
+    }
+}
@@ -0,0 +1,125 @@
+pub mod perspective;
+pub mod orthographic;
+pub mod fisheye;
+pub mod equirectangular;
+
+use crate::camera::Camera;
+use crate::util::ray::Ray;
+use crate::util::sampler::Sampler;
+
+use equirectangular::Equirectangular;
+use fisheye::Fisheye;
+use orthographic::Orthographic;
+use perspective::Perspective;
+
+// Maps image positions to primary rays. x and y are continuous pixel coordinates,
+// (0, 0) is the top left corner of the image and (image_width, image_height) the bottom right.
+pub trait Projection: Send + Sync {
+    // None for positions outside of the projection, which stay black
+    fn cast_ray(&self, camera: &Camera, x: f64, y: f64, sampler: &mut dyn Sampler) -> Option<Ray>;
+}
+
+// The projections as plain data, so a CameraBuilder can carry one around.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ProjectionKind {
+    Perspective,
+    Orthographic { view_width: f64 },
+    Fisheye { fov: f64 },
+    Equirectangular,
+}
+
+impl ProjectionKind {
+    pub fn validate(&self) -> Result<(), String> {
+        match *self {
+            ProjectionKind::Orthographic { view_width } if !(view_width > 0.0 && view_width.is_finite()) => {
+                Err(format!("orthographic view width must be positive, got {}", view_width))
+            }
+            ProjectionKind::Fisheye { fov } if !(fov > 0.0 && fov <= 360.0) => {
+                Err(format!("fisheye field of view must be in (0, 360] degrees, got {}", fov))
+            }
+            _ => Ok(()),
+        }
+    }
+
+    pub fn build(&self) -> Box<dyn Projection> {
+        match *self {
+            ProjectionKind::Perspective => Box::new(Perspective),
+            ProjectionKind::Orthographic { view_width } => Box::new(Orthographic::new(view_width)),
+            ProjectionKind::Fisheye { fov } => Box::new(Fisheye::new(fov)),
+            ProjectionKind::Equirectangular => Box::new(Equirectangular),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::CameraBuilder;
+    use crate::hittable::sphere::Sphere;
+    use crate::material::diffusive::Diffusive;
+    use crate::util::sampler::Independent;
+    use crate::util::vec3::{Color, Point3, Vec3};
+    use crate::world::World;
+
+    // looking down -z from the origin, 40x20 pixels
+    fn camera(projection: ProjectionKind) -> Camera {
+        let sphere = Sphere::new(Point3::new(0.0, 0.0, -5.0), 1.0, Diffusive::new(Color::ones()));
+        let world = World { hittables: vec![Box::new(sphere)], material_ids: vec![], fog: None };
+        CameraBuilder::new().image_width(40).aspect_ratio(2.0).projection(projection).build(world).unwrap()
+    }
+
+    fn ray(camera: &Camera, x: f64, y: f64) -> Option<Ray> {
+        camera.projection.cast_ray(camera, x, y, &mut Independent::new(0))
+    }
+
+    #[test]
+    fn test_perspective_center() {
+        let camera = camera(ProjectionKind::Perspective);
+        let center = ray(&camera, 20.0, 10.0).unwrap();
+        assert!((center.dir - Vec3::new(0.0, 0.0, -1.0)).length() < 1e-9);
+        assert!(ray(&camera, 0.0, 0.0).unwrap().dir.x < 0.0);
+    }
+
+    #[test]
+    fn test_orthographic_rays_are_parallel() {
+        let camera = camera(ProjectionKind::Orthographic { view_width: 4.0 });
+        let corner = ray(&camera, 0.0, 0.0).unwrap();
+        let center = ray(&camera, 20.0, 10.0).unwrap();
+        assert_eq!(corner.dir, camera.direction);
+        assert_eq!(center.dir, camera.direction);
+        // the image is view_width wide and has square pixels
+        assert!((center.ori - camera.center).length() < 1e-12);
+        assert!((corner.ori - Point3::new(-2.0, 1.0, 0.0)).length() < 1e-12);
+    }
+
+    #[test]
+    fn test_fisheye_circle() {
+        let camera = camera(ProjectionKind::Fisheye { fov: 180.0 });
+        assert!((ray(&camera, 20.0, 10.0).unwrap().dir - camera.direction).length() < 1e-12);
+        // the rim of the image circle looks sideways, the corners are outside of it
+        let rim = ray(&camera, 30.0, 10.0).unwrap();
+        assert!(Vec3::dot(&rim.dir, &camera.direction).abs() < 1e-12);
+        assert!(ray(&camera, 0.0, 0.0).is_none());
+        assert!(ray(&camera, 31.0, 10.0).is_none());
+    }
+
+    #[test]
+    fn test_equirectangular_full_circle() {
+        let camera = camera(ProjectionKind::Equirectangular);
+        let at = |x: f64, y: f64| ray(&camera, x, y).unwrap().dir;
+        assert!((at(20.0, 10.0) - camera.direction).length() < 1e-12);
+        // a quarter turn to either side, half a turn at both edges, straight up at the top
+        assert!((at(30.0, 10.0) - camera.u_unit).length() < 1e-12);
+        assert!((at(10.0, 10.0) + camera.u_unit).length() < 1e-12);
+        assert!((at(0.0, 10.0) + camera.direction).length() < 1e-12);
+        assert!((at(40.0, 10.0) + camera.direction).length() < 1e-12);
+        assert!((at(20.0, 0.0) + camera.v_unit).length() < 1e-12);
+    }
+
+    #[test]
+    fn test_invalid_projections() {
+        assert!(ProjectionKind::Orthographic { view_width: 0.0 }.validate().is_err());
+        assert!(ProjectionKind::Fisheye { fov: 400.0 }.validate().is_err());
+        assert!(ProjectionKind::Fisheye { fov: 360.0 }.validate().is_ok());
+    }
+}
@@ -0,0 +1,28 @@
+use super::Projection;
+use crate::camera::Camera;
+use crate::util::ray::Ray;
+use crate::util::sampler::Sampler;
+use crate::util::vec3::Vec3;
+use std::f64::consts::PI;
+
+// 360 degree panorama, longitude along x and latitude along y, best used with a 2:1 image
+#[derive(Clone, Copy)]
+pub struct Equirectangular;
+
+impl Equirectangular {
+    // direction of the panorama at the given image position, relative to the camera basis
+    pub fn direction(camera: &Camera, x: f64, y: f64) -> Vec3 {
+        let longitude = (x / camera.image_width as f64 - 0.5) * 2.0 * PI;
+        let latitude = (0.5 - y / camera.image_height as f64) * PI;
+        let up = -camera.v_unit;
+        camera.direction * (latitude.cos() * longitude.cos())
+            + camera.u_unit * (latitude.cos() * longitude.sin())
+            + up * latitude.sin()
+    }
+}
+
+impl Projection for Equirectangular {
+    fn cast_ray(&self, camera: &Camera, x: f64, y: f64, _sampler: &mut dyn Sampler) -> Option<Ray> {
+        Some(Ray::new(camera.center, Self::direction(camera, x, y).unit()))
+    }
+}
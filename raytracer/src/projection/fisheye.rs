@@ -0,0 +1,36 @@
+use super::Projection;
+use crate::camera::Camera;
+use crate::util::ray::Ray;
+use crate::util::sampler::Sampler;
+
+// Equidistant fisheye, the angle to the viewing direction grows linearly with the
+// distance to the image center. fov is the angle covered by the inscribed circle in degrees.
+#[derive(Clone, Copy)]
+pub struct Fisheye {
+    pub fov: f64,
+}
+
+impl Fisheye {
+    pub fn new(fov: f64) -> Self {
+        Self { fov }
+    }
+}
+
+impl Projection for Fisheye {
+    fn cast_ray(&self, camera: &Camera, x: f64, y: f64, _sampler: &mut dyn Sampler) -> Option<Ray> {
+        let half_width = 0.5 * camera.image_width as f64;
+        let half_height = 0.5 * camera.image_height as f64;
+        let radius = half_width.min(half_height);
+        let px = (x - half_width) / radius;
+        let py = (y - half_height) / radius;
+        let r = (px * px + py * py).sqrt();
+        if r > 1.0 {
+            return None;
+        }
+        let theta = r * 0.5 * self.fov.to_radians();
+        let phi = py.atan2(px);
+        let direction = camera.direction * theta.cos()
+            + (camera.u_unit * phi.cos() + camera.v_unit * phi.sin()) * theta.sin();
+        Some(Ray::new(camera.center, direction.unit()))
+    }
+}
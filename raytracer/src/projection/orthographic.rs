@@ -0,0 +1,26 @@
+use super::Projection;
+use crate::camera::Camera;
+use crate::util::ray::Ray;
+use crate::util::sampler::Sampler;
+
+// parallel rays along the viewing direction, view_width is the width of the image in world units
+#[derive(Clone, Copy)]
+pub struct Orthographic {
+    pub view_width: f64,
+}
+
+impl Orthographic {
+    pub fn new(view_width: f64) -> Self {
+        Self { view_width }
+    }
+}
+
+impl Projection for Orthographic {
+    fn cast_ray(&self, camera: &Camera, x: f64, y: f64, _sampler: &mut dyn Sampler) -> Option<Ray> {
+        let scale = self.view_width / camera.image_width as f64;
+        let origin = camera.center
+            + camera.u_unit * ((x - 0.5 * camera.image_width as f64) * scale)
+            + camera.v_unit * ((y - 0.5 * camera.image_height as f64) * scale);
+        Some(Ray::new(origin, camera.direction))
+    }
+}
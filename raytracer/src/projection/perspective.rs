@@ -0,0 +1,15 @@
+use super::Projection;
+use crate::camera::Camera;
+use crate::util::ray::Ray;
+use crate::util::sampler::Sampler;
+
+// pinhole or thin lens projection through the pixel grid of the camera
+#[derive(Clone, Copy)]
+pub struct Perspective;
+
+impl Projection for Perspective {
+    fn cast_ray(&self, camera: &Camera, x: f64, y: f64, sampler: &mut dyn Sampler) -> Option<Ray> {
+        let pixel_loc = camera.pixel0_loc + camera.du * (x - 0.5) + camera.dv * (y - 0.5);
+        Some(camera.cast_ray(&pixel_loc, sampler))
+    }
+}
@@ -0,0 +1,615 @@
+use image::RgbImage;
+use indicatif::ProgressBar;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::mpsc;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use threadpool::ThreadPool;
+
+use crate::camera::Camera;
+use crate::checkpoint::Checkpoint;
+use crate::film::{Estimator, Film, PixelStats, Splat, TileSamples};
+use crate::filter::Filter;
+use crate::post::PostEffects;
+use crate::tonemap::ToneMapping;
+use crate::util::const_value;
+use crate::util::sampler::{Sampler, SamplerKind, DEFAULT_SEED};
+use crate::util::vec3::Color;
+
+// A rectangle of the image, tiles at the right and bottom border may be smaller.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct Tile {
+    pub x: u32,
+    pub y: u32,
+    pub width: u32,
+    pub height: u32,
+}
+
+// Split the image into square tiles ordered in a spiral around the center, so the
+// interesting middle of the picture is finished first.
+pub fn spiral_tiles(image_width: u32, image_height: u32, tile_size: u32) -> Vec<Tile> {
+    assert!(tile_size > 0, "tile size must be positive");
+    let nx = ((image_width + tile_size - 1) / tile_size) as i64;
+    let ny = ((image_height + tile_size - 1) / tile_size) as i64;
+    let mut tiles = Vec::with_capacity((nx * ny) as usize);
+    let push = |tiles: &mut Vec<Tile>, tx: i64, ty: i64| {
+        if tx < 0 || ty < 0 || tx >= nx || ty >= ny {
+            return;
+        }
+        let x = tx as u32 * tile_size;
+        let y = ty as u32 * tile_size;
+        tiles.push(Tile {
+            x,
+            y,
+            width: tile_size.min(image_width - x),
+            height: tile_size.min(image_height - y),
+        });
+    };
+
+    // walk right 1, down 1, left 2, up 2, right 3, ... skipping steps outside the grid
+    let (mut tx, mut ty) = ((nx - 1) / 2, (ny - 1) / 2);
+    let directions = [(1, 0), (0, 1), (-1, 0), (0, -1)];
+    let mut leg = 0;
+    push(&mut tiles, tx, ty);
+    while (tiles.len() as i64) < nx * ny {
+        let (dx, dy) = directions[leg % 4];
+        for _ in 0..leg / 2 + 1 {
+            tx += dx;
+            ty += dy;
+            push(&mut tiles, tx, ty);
+        }
+        leg += 1;
+    }
+    tiles
+}
+
+#[derive(Clone, Debug)]
+pub struct RenderSettings {
+    pub tile_size: u32,
+    pub n_threads: usize,
+    pub samples_per_pixel: u32,
+    // Progressive rendering: the whole image gets this many samples per pass,
+    // so a usable picture is available long before the render is done.
+    pub samples_per_pass: u32,
+    pub preview_path: Option<String>,
+    // write the preview every so often instead of after every pass
+    pub preview_interval: Option<Duration>,
+    pub adaptive: Option<Adaptive>,
+    // image of the samples spent per pixel, written at the end of the render
+    pub heatmap_path: Option<String>,
+    // no new pass is started once the budget is used up
+    pub time_budget: Option<Duration>,
+    pub cancel: CancelHandle,
+    // the film is saved here after every pass, or at most once per checkpoint_interval
+    pub checkpoint_path: Option<String>,
+    pub checkpoint_interval: Option<Duration>,
+    // the image only depends on the seed, not on the number of threads, and with filters
+    // wider than a pixel on the tile size only up to rounding
+    pub seed: u64,
+    pub sampler: SamplerKind,
+    // sample count the sampler stratifies for, samples_per_pixel unless a resumed render
+    // was started with a different one
+    pub sampler_samples: Option<u32>,
+    // reconstruction filter the samples are splatted with
+    pub filter: Filter,
+    // how the preview and display images are made from the film
+    pub post: PostEffects,
+    pub tone_mapping: ToneMapping,
+    // Firefly suppression: every sample is scaled down to at most this much radiance per
+    // channel, which removes the noise of rare bright paths but also some of their energy.
+    pub max_radiance: Option<f64>,
+    pub estimator: Estimator,
+}
+
+// Adaptive sampling: after min_samples, pixels whose relative standard error drops
+// below target_error are left out of the following passes. samples_per_pixel is the
+// maximum any pixel gets.
+#[derive(Clone, Copy, Debug)]
+pub struct Adaptive {
+    pub min_samples: u32,
+    pub target_error: f64,
+}
+
+impl Default for RenderSettings {
+    fn default() -> Self {
+        Self {
+            tile_size: 32,
+            n_threads: 4,
+            samples_per_pixel: const_value::RAY_PER_PIXEL as u32,
+            samples_per_pass: const_value::RAY_PER_PIXEL as u32,
+            preview_path: None,
+            preview_interval: None,
+            adaptive: None,
+            heatmap_path: None,
+            time_budget: None,
+            cancel: CancelHandle::new(),
+            checkpoint_path: None,
+            checkpoint_interval: None,
+            seed: DEFAULT_SEED,
+            sampler: SamplerKind::Independent,
+            sampler_samples: None,
+            filter: Filter::default(),
+            post: PostEffects::default(),
+            tone_mapping: ToneMapping::default(),
+            max_radiance: None,
+            estimator: Estimator::Mean,
+        }
+    }
+}
+
+// Shared flag to stop a render from another thread. The workers finish the tile they
+// are on and the render returns the film as far as it got.
+#[derive(Clone, Debug, Default)]
+pub struct CancelHandle {
+    cancelled: Arc<AtomicBool>,
+}
+
+impl CancelHandle {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    pub fn cancel(&self) {
+        self.cancelled.store(true, Ordering::Relaxed);
+    }
+
+    pub fn is_cancelled(&self) -> bool {
+        self.cancelled.load(Ordering::Relaxed)
+    }
+}
+
+// Run `work` on every tile with n_threads workers that keep pulling the next tile from
+// a shared queue, so threads which got cheap tiles simply take more of them. Results
+// are handed to `collect` on the calling thread in tile order, each once it and all tiles
+// before it are done, so sums over overlapping tiles do not depend on the thread timing.
+// The camera is only shared while rendering, so it can be modified again afterwards.
+pub(crate) fn render_tiles<T, F, C>(
+    camera: &Arc<Camera>,
+    tiles: Vec<Tile>,
+    n_threads: usize,
+    cancel: &CancelHandle,
+    work: F,
+    mut collect: C,
+) where
+    T: Send + 'static,
+    F: Fn(&Camera, Tile) -> T + Send + Sync + 'static,
+    C: FnMut(Tile, T),
+{
+    let tiles = Arc::new(tiles);
+    let work = Arc::new(work);
+    let next_tile = Arc::new(AtomicUsize::new(0));
+    let (tx, rx) = mpsc::channel();
+    let pool = ThreadPool::new(n_threads);
+
+    for _ in 0..n_threads {
+        let tx = tx.clone();
+        let camera = camera.clone();
+        let tiles = tiles.clone();
+        let work = work.clone();
+        let next_tile = next_tile.clone();
+        let cancel = cancel.clone();
+        pool.execute(move || {
+            loop {
+                let index = next_tile.fetch_add(1, Ordering::Relaxed);
+                let tile = match tiles.get(index) {
+                    Some(&tile) => tile,
+                    None => break,
+                };
+                if cancel.is_cancelled() {
+                    break;
+                }
+                tx.send((index, tile, work(&camera, tile))).expect("failed to send result");
+            }
+        })
+    }
+    drop(tx);
+
+    // results which arrive early wait for the ones before them
+    let mut pending: Vec<Option<(Tile, T)>> = (0..tiles.len()).map(|_| None).collect();
+    let mut next = 0;
+    for (index, tile, result) in rx.iter() {
+        pending[index] = Some((tile, result));
+        while let Some((tile, result)) = pending.get_mut(next).and_then(Option::take) {
+            collect(tile, result);
+            next += 1;
+        }
+    }
+    pool.join();
+    // a cancelled render leaves gaps, the tiles after them are still kept
+    for (tile, result) in pending.into_iter().flatten() {
+        collect(tile, result);
+    }
+}
+
+// the tile grown by `margin` pixels on every side, clipped to the image
+fn grow_tile(tile: Tile, margin: u32, image_width: u32, image_height: u32) -> Tile {
+    let x = tile.x.saturating_sub(margin);
+    let y = tile.y.saturating_sub(margin);
+    Tile {
+        x,
+        y,
+        width: (tile.x + tile.width + margin).min(image_width) - x,
+        height: (tile.y + tile.height + margin).min(image_height) - y,
+    }
+}
+
+// what every sample of a pass does besides tracing the ray
+#[derive(Clone, Copy, Debug)]
+struct SampleSettings {
+    filter: Filter,
+    batches: u32,
+    max_radiance: Option<f64>,
+}
+
+// scaled down as a whole so the hue stays the same
+fn clamp_radiance(color: Color, max_radiance: f64) -> Color {
+    let peak = color.x.max(color.y).max(color.z);
+    if peak > max_radiance {
+        color * (max_radiance / peak)
+    } else {
+        color
+    }
+}
+
+// samples first_sample..first_sample + samples for every active pixel of the tile,
+// splatted with the filter into the pixels around them
+fn sample_tile(
+    camera: &Camera,
+    tile: Tile,
+    sampler: &dyn Sampler,
+    settings: &SampleSettings,
+    first_sample: u32,
+    samples: u32,
+    active: &[bool],
+) -> TileSamples {
+    let mut sampler = sampler.clone_box();
+    let filter = &settings.filter;
+    let batches = settings.batches;
+    let margin = filter.margin();
+    let region = grow_tile(tile, margin, camera.image_width, camera.image_height);
+    let mut stats = vec![PixelStats::default(); (tile.width * tile.height) as usize];
+    let mut splats = vec![Splat::default(); (region.width * region.height * batches) as usize];
+    for j in 0..tile.height {
+        for i in 0..tile.width {
+            let (x, y) = (tile.x + i, tile.y + j);
+            if !active[(y * camera.image_width + x) as usize] {
+                continue;
+            }
+            let neighbours = grow_tile(Tile { x, y, width: 1, height: 1 }, margin, camera.image_width, camera.image_height);
+            for k in first_sample..first_sample + samples {
+                sampler.start_pixel_sample(x, y, k);
+                let (dx, dy) = sampler.next_2d();
+                let (px, py) = (x as f64 + dx, y as f64 + dy);
+                let mut color = camera.sample(px, py, sampler.as_mut());
+                if let Some(max_radiance) = settings.max_radiance {
+                    color = clamp_radiance(color, max_radiance);
+                }
+                stats[(j * tile.width + i) as usize].add(color);
+                for sy in neighbours.y..neighbours.y + neighbours.height {
+                    for sx in neighbours.x..neighbours.x + neighbours.width {
+                        let weight = filter.evaluate(px - (sx as f64 + 0.5), py - (sy as f64 + 0.5));
+                        if weight != 0.0 {
+                            let pixel = (sy - region.y) * region.width + sx - region.x;
+                            splats[(pixel * batches + k % batches) as usize].add(color, weight);
+                        }
+                    }
+                }
+            }
+        }
+    }
+    TileSamples { stats, region, splats }
+}
+
+// the display image with post effects and tone mapping
+pub fn display_image(film: &Film, settings: &RenderSettings) -> RgbImage {
+    settings.post.apply(&film.to_hdr()).to_image(&settings.tone_mapping)
+}
+
+fn save_preview(film: &Film, path: &str, settings: &RenderSettings) {
+    if let Err(e) = display_image(film, settings).save(path) {
+        println!("failed to write preview {}: {}", path, e);
+    }
+}
+
+// pixels which still need samples after `done` samples each
+fn active_pixels(film: &Film, settings: &RenderSettings, done: u32) -> Vec<bool> {
+    let mut active = vec![done < settings.samples_per_pixel; (film.width * film.height) as usize];
+    if let Some(adaptive) = settings.adaptive {
+        if done >= adaptive.min_samples {
+            for j in 0..film.height {
+                for i in 0..film.width {
+                    let index = (j * film.width + i) as usize;
+                    active[index] = active[index] && film.stats(i, j).relative_error() > adaptive.target_error;
+                }
+            }
+        }
+    }
+    active
+}
+
+// Render in passes of samples_per_pass samples over the whole image, accumulating into
+// a float film. The preview image is rewritten after every pass or every preview_interval.
+// A cancelled or out of time render still returns everything sampled so far.
+pub fn render_progressive(camera: &Arc<Camera>, settings: &RenderSettings) -> Film {
+    let film = Film::with_estimator(camera.image_width, camera.image_height, settings.estimator);
+    render_passes(camera, settings, film, 0)
+}
+
+// Continue a checkpointed render up to settings.samples_per_pixel.
+pub fn resume_progressive(camera: &Arc<Camera>, settings: &RenderSettings, checkpoint: Checkpoint) -> Result<Film, String> {
+    if (checkpoint.film.width, checkpoint.film.height) != (camera.image_width, camera.image_height) {
+        return Err(format!(
+            "checkpoint is {}x{} but the image is {}x{}",
+            checkpoint.film.width, checkpoint.film.height, camera.image_width, camera.image_height
+        ));
+    }
+    println!("Resuming from {} samples per pixel", checkpoint.done);
+    Ok(render_passes(camera, settings, checkpoint.film, checkpoint.done))
+}
+
+fn render_passes(camera: &Arc<Camera>, settings: &RenderSettings, mut film: Film, mut done: u32) -> Film {
+    assert!(settings.samples_per_pass > 0, "a pass needs at least one sample");
+    let start = Instant::now();
+    let first_pass = done;
+    let tiles = spiral_tiles(camera.image_width, camera.image_height, settings.tile_size);
+    let remaining = settings.samples_per_pixel.saturating_sub(done);
+    let n_passes = (remaining + settings.samples_per_pass - 1) / settings.samples_per_pass;
+    let bar = ProgressBar::new(n_passes as u64 * tiles.len() as u64);
+    let mut last_preview = Instant::now();
+    let mut last_checkpoint = Instant::now();
+    let sampler_samples = settings.sampler_samples.unwrap_or(settings.samples_per_pixel);
+    let sampler: Arc<dyn Sampler> = Arc::from(settings.sampler.build(settings.seed, sampler_samples));
+
+    // converged pixels never become active again, so all active pixels have `done` samples
+    loop {
+        if settings.cancel.is_cancelled() {
+            println!("Render cancelled after {} samples per pixel", done);
+            break;
+        }
+        if let Some(budget) = settings.time_budget {
+            if done > first_pass && start.elapsed() >= budget {
+                println!("Time budget used up after {} samples per pixel", done);
+                break;
+            }
+        }
+        let active = active_pixels(&film, settings, done);
+        let pass_tiles: Vec<Tile> = tiles
+            .iter()
+            .filter(|tile| {
+                (tile.y..tile.y + tile.height)
+                    .any(|y| (tile.x..tile.x + tile.width).any(|x| active[(y * film.width + x) as usize]))
+            })
+            .cloned()
+            .collect();
+        if pass_tiles.is_empty() {
+            break;
+        }
+        bar.inc((tiles.len() - pass_tiles.len()) as u64);
+
+        let samples = settings.samples_per_pass.min(settings.samples_per_pixel - done);
+        let active = Arc::new(active);
+        let sample_settings =
+            SampleSettings { filter: settings.filter, batches: film.estimator().batches(), max_radiance: settings.max_radiance };
+        let (sampler, first_sample) = (sampler.clone(), done);
+        render_tiles(
+            camera,
+            pass_tiles,
+            settings.n_threads,
+            &settings.cancel,
+            move |camera, tile| sample_tile(camera, tile, sampler.as_ref(), &sample_settings, first_sample, samples, &active),
+            |tile, samples| {
+                film.add_tile(tile, &samples);
+                bar.inc(1);
+                if let (Some(path), Some(interval)) = (&settings.preview_path, settings.preview_interval) {
+                    if last_preview.elapsed() >= interval {
+                        save_preview(&film, path, settings);
+                        last_preview = Instant::now();
+                    }
+                }
+            },
+        );
+        if settings.cancel.is_cancelled() {
+            // the pass is incomplete, keep the last checkpoint
+            continue;
+        }
+        done += samples;
+        if let (Some(path), None) = (&settings.preview_path, settings.preview_interval) {
+            save_preview(&film, path, settings);
+        }
+        if let Some(path) = &settings.checkpoint_path {
+            let due = match settings.checkpoint_interval {
+                Some(interval) => last_checkpoint.elapsed() >= interval,
+                None => true,
+            };
+            if due {
+                if let Err(e) = Checkpoint::new(&film, done, settings).save(path) {
+                    println!("{}", e);
+                }
+                last_checkpoint = Instant::now();
+            }
+        }
+    }
+    bar.finish();
+
+    if let Some(path) = &settings.heatmap_path {
+        if let Err(e) = film.sample_heatmap(settings.samples_per_pixel).save(path) {
+            println!("failed to write sample heatmap {}: {}", path, e);
+        }
+    }
+    film
+}
+
+pub fn render_multi_thread(camera: &Arc<Camera>, tile_size: u32, n_threads: usize) -> RgbImage {
+    let settings = RenderSettings {
+        tile_size,
+        n_threads,
+        ..RenderSettings::default()
+    };
+    display_image(&render_progressive(camera, &settings), &settings)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::CameraBuilder;
+    use crate::filter::FilterKind;
+    use crate::hittable::sphere::Sphere;
+    use crate::hittable::HitRecord;
+    use crate::material::diffusive::Diffusive;
+    use crate::material::Material;
+    use crate::util::vec3::Point3;
+    use crate::world::World;
+
+    fn small_camera() -> Arc<Camera> {
+        let sphere = Sphere::new(Point3::new(0.0, 0.0, -2.0), 0.5, Diffusive::new(Color::new(0.5, 0.5, 0.5)));
+        let world = World { hittables: vec![Box::new(sphere)], material_ids: vec![], fog: None };
+        let camera = CameraBuilder::new()
+            .image_width(8)
+            .aspect_ratio(1.0)
+            .background_color(Color::ones())
+            .build(world)
+            .unwrap();
+        Arc::new(camera)
+    }
+
+    #[test]
+    fn test_spiral_tiles_cover_image() {
+        for &(width, height, size) in &[(400, 225, 32), (400, 225, 400), (7, 3, 2), (1, 1, 16), (33, 65, 16)] {
+            let tiles = spiral_tiles(width, height, size);
+            let mut count = vec![0; (width * height) as usize];
+            for tile in &tiles {
+                assert!(tile.width > 0 && tile.height > 0);
+                for j in tile.y..tile.y + tile.height {
+                    for i in tile.x..tile.x + tile.width {
+                        count[(j * width + i) as usize] += 1;
+                    }
+                }
+            }
+            assert!(count.iter().all(|&c| c == 1), "{}x{} with tile size {}", width, height, size);
+        }
+    }
+
+    #[test]
+    fn test_spiral_starts_in_center() {
+        let tiles = spiral_tiles(90, 90, 30);
+        assert_eq!(tiles[0], Tile { x: 30, y: 30, width: 30, height: 30 });
+        assert_eq!(tiles[1], Tile { x: 60, y: 30, width: 30, height: 30 });
+        assert_eq!(tiles[2], Tile { x: 60, y: 60, width: 30, height: 30 });
+    }
+
+    #[test]
+    fn test_cancelled_render_returns_partial_film() {
+        let camera = small_camera();
+        let settings = RenderSettings { samples_per_pixel: 4, samples_per_pass: 1, ..RenderSettings::default() };
+        let film = render_progressive(&camera, &settings);
+        assert_eq!(film.samples(3, 5), 4);
+
+        settings.cancel.cancel();
+        let film = render_progressive(&camera, &settings);
+        assert_eq!(film.samples(3, 5), 0);
+    }
+
+    // cancels the render the first time a ray hits it
+    struct Tripwire(CancelHandle);
+
+    impl Material for Tripwire {
+        fn emitted(&self, _hit_record: &HitRecord) -> Color {
+            self.0.cancel();
+            Color::zero()
+        }
+    }
+
+    #[test]
+    fn test_cancel_during_pass() {
+        let settings = RenderSettings { samples_per_pixel: 8, samples_per_pass: 2, tile_size: 2, n_threads: 1, ..RenderSettings::default() };
+        let sphere = Sphere::new(Point3::new(0.0, 0.0, -2.0), 0.5, Tripwire(settings.cancel.clone()));
+        let world = World { hittables: vec![Box::new(sphere)], material_ids: vec![], fog: None };
+        let camera = Arc::new(CameraBuilder::new().image_width(8).aspect_ratio(1.0).build(world).unwrap());
+        let film = render_progressive(&camera, &settings);
+
+        // the first tile already hits the sphere, it is finished but no other tile is started
+        // and the render does not go on with the next pass
+        let first = spiral_tiles(8, 8, 2)[0];
+        let in_first = |i: u32, j: u32| (first.x..first.x + 2).contains(&i) && (first.y..first.y + 2).contains(&j);
+        for k in 0..64 {
+            let (i, j) = (k % 8, k / 8);
+            assert_eq!(film.samples(i, j), if in_first(i, j) { 2 } else { 0 }, "pixel ({}, {})", i, j);
+        }
+    }
+
+    #[test]
+    fn test_independent_of_threads_and_tiles() {
+        let camera = small_camera();
+        let settings = RenderSettings { samples_per_pixel: 6, samples_per_pass: 3, seed: 7, ..RenderSettings::default() };
+        let a = render_progressive(&camera, &RenderSettings { n_threads: 1, tile_size: 3, ..settings.clone() });
+        let b = render_progressive(&camera, &RenderSettings { n_threads: 3, tile_size: 5, ..settings.clone() });
+        let c = render_progressive(&camera, &RenderSettings { seed: 8, ..settings });
+        let pixels = |film: &Film| (0..8 * 8).map(|k| film.pixel(k % 8, k / 8)).collect::<Vec<_>>();
+        assert_eq!(pixels(&a), pixels(&b));
+        assert_ne!(pixels(&a), pixels(&c));
+    }
+
+    #[test]
+    fn test_filter_splats_across_tiles() {
+        let camera = small_camera();
+        let filter = Filter::new(FilterKind::Tent, 1.5).unwrap();
+        let settings = RenderSettings { samples_per_pixel: 2, filter, ..RenderSettings::default() };
+        let pixels = |film: &Film| (0..64).map(|k| film.pixel(k % 8, k / 8)).collect::<Vec<_>>();
+        let render = |n_threads: usize, tile_size: u32| pixels(&render_progressive(&camera, &RenderSettings { n_threads, tile_size, ..settings.clone() }));
+        let a = render(1, 3);
+        for &tile_size in &[2, 3, 8] {
+            // tiles are merged in a fixed order, so the threads do not change a bit
+            let one = render(1, tile_size);
+            assert_eq!(one, render(3, tile_size));
+            assert_eq!(one, render(8, tile_size));
+            // the tile size groups the floating point sums of overlapping splats differently
+            assert!(one.iter().zip(&a).all(|(p, q)| (*p - *q).length() < 1e-9));
+        }
+        let boxed = pixels(&render_progressive(&camera, &RenderSettings { filter: Filter::default(), ..settings }));
+        assert!(a.iter().zip(&boxed).any(|(p, q)| (*p - *q).length() > 1e-3));
+    }
+
+    #[test]
+    fn test_samplers_independent_of_threads_and_tiles() {
+        let camera = small_camera();
+        for &sampler in &SamplerKind::ALL[1..] {
+            let settings = RenderSettings { samples_per_pixel: 4, samples_per_pass: 2, sampler, ..RenderSettings::default() };
+            let a = render_progressive(&camera, &RenderSettings { n_threads: 1, tile_size: 3, ..settings.clone() });
+            let b = render_progressive(&camera, &RenderSettings { n_threads: 2, tile_size: 8, ..settings });
+            assert_eq!((0..64).map(|k| a.pixel(k % 8, k / 8)).collect::<Vec<_>>(), (0..64).map(|k| b.pixel(k % 8, k / 8)).collect::<Vec<_>>());
+        }
+    }
+
+    #[test]
+    fn test_radiance_clamp_and_estimator() {
+        // the background is white, so every sample has radiance 1
+        let camera = small_camera();
+        let settings = RenderSettings { samples_per_pixel: 16, samples_per_pass: 5, ..RenderSettings::default() };
+        let clamped = render_progressive(&camera, &RenderSettings { max_radiance: Some(0.25), ..settings.clone() });
+        assert!((0..64).all(|k| clamped.pixel(k % 8, k / 8).x <= 0.25 + 1e-12));
+        assert!((clamped.pixel(0, 0) - Color::ones() * 0.25).length() < 1e-12);
+
+        let robust = render_progressive(&camera, &RenderSettings { estimator: Estimator::MedianOfMeans, ..settings });
+        assert_eq!(robust.estimator(), Estimator::MedianOfMeans);
+        assert!((robust.pixel(0, 0) - Color::ones()).length() < 1e-12);
+        assert_eq!(robust.samples(4, 4), 16);
+    }
+    #[test]
+    fn test_resume_keeps_the_sampler() {
+        // stopped at 4 samples and resumed to 8 samples, the first 4 stay stratified for 4
+        let camera = small_camera();
+        let settings = RenderSettings { samples_per_pixel: 4, samples_per_pass: 2, sampler: SamplerKind::Stratified, ..RenderSettings::default() };
+        let film = render_progressive(&camera, &settings);
+        let checkpoint = Checkpoint::from_bytes(&Checkpoint::new(&film, 4, &settings).to_bytes()).unwrap();
+        let mut resumed_settings = RenderSettings { samples_per_pixel: 8, ..RenderSettings::default() };
+        checkpoint.apply_settings(&mut resumed_settings);
+        assert_eq!((resumed_settings.samples_per_pixel, resumed_settings.sampler_samples), (8, Some(4)));
+        let resumed = resume_progressive(&camera, &resumed_settings, checkpoint).unwrap();
+
+        let whole = render_progressive(&camera, &RenderSettings { samples_per_pixel: 8, sampler_samples: Some(4), ..settings.clone() });
+        let rebuilt = render_progressive(&camera, &RenderSettings { samples_per_pixel: 8, ..settings });
+        let pixels = |film: &Film| (0..64).map(|k| film.pixel(k % 8, k / 8)).collect::<Vec<_>>();
+        assert_eq!(pixels(&resumed), pixels(&whole));
+        assert_ne!(pixels(&resumed), pixels(&rebuilt));
+    }
+}
@@ -0,0 +1,501 @@
+// Loader for the json scene descriptions in `data/`.
+// Every node is an object with a "type" field, e.g.
+// { "type": "Sphere", "center": { "x": 0, "y": 0, "z": -1 }, "radius": 0.5, "material": { ... } }
+// Materials used by several objects can be named in a "materials" table next to "objects"
+// and referred to by name, e.g. "material": "gold", so they share one material ID.
+
+use serde_json::{Map, Value};
+use std::fs;
+
+use crate::animation::{Animation, CameraKeyframe, CameraPath, Interpolation};
+use crate::camera::CameraBuilder;
+use crate::projection::ProjectionKind;
+use crate::hittable::Hittable;
+use crate::hittable::sphere::Sphere;
+use crate::hittable::quad::Quad;
+use crate::hittable::constant_medium::ConstantMedium;
+use crate::hittable::grid_medium::GridMedium;
+use crate::hittable::moving::Moving;
+use crate::material::Material;
+use crate::material::diffusive::Diffusive;
+use crate::material::dieletric::{Dieletric, Dispersion};
+use crate::material::light::Light;
+use crate::material::metal::Metal;
+use crate::material::principled::Principled;
+use crate::material::mix::Mix;
+use crate::material::coated::Coated;
+use crate::texture::Texture;
+use crate::texture::constant::ConstantTexture;
+use crate::texture::checker::CheckerTexture;
+use crate::util::vec3::{Color, Vec3};
+use crate::util::voxel_grid::VoxelGrid;
+use crate::world::{Fog, World};
+
+pub struct Scene {
+    pub world: World,
+    pub camera: Option<CameraBuilder>,
+    pub animation: Option<Animation>,
+}
+
+pub fn load_scene(path: &str) -> Result<Scene, String> {
+    let text = fs::read_to_string(path).map_err(|e| format!("failed to read {}: {}", path, e))?;
+    parse_scene(&text).map_err(|e| format!("{}: {}", path, e))
+}
+
+pub fn parse_scene(text: &str) -> Result<Scene, String> {
+    let scene: Value = serde_json::from_str(text).map_err(|e| format!("failed to parse: {}", e))?;
+    let no_materials = Map::new();
+    let materials = match scene.get("materials") {
+        Some(materials) => materials.as_object().ok_or_else(|| "field \"materials\" is not an object".to_string())?,
+        None => &no_materials,
+    };
+    let mut objects = vec![];
+    parse_hittable(field(&scene, "objects")?, materials, &mut objects)?;
+    let (hittables, material_ids) = objects.into_iter().unzip();
+    let mut world = World { hittables, material_ids, fog: None };
+    if let Some(fog) = scene.get("fog") {
+        world.fog = Some(Fog::new(parse_f64(fog, "density")?, parse_color(field(fog, "albedo")?)?));
+    }
+    let camera = match scene.get("camera") {
+        Some(camera) => Some(parse_camera(camera)?),
+        None => None,
+    };
+    let animation = match scene.get("animation") {
+        Some(animation) => {
+            // the path shares the up direction of the camera unless it has its own
+            let vup = match animation.get("vup").or_else(|| scene.get("camera").and_then(|c| c.get("vup"))) {
+                Some(vup) => parse_vec3(vup)?,
+                None => Vec3::new(0.0, 1.0, 0.0),
+            };
+            Some(parse_animation(animation, vup)?)
+        }
+        None => None,
+    };
+    Ok(Scene { world, camera, animation })
+}
+
+pub fn load_world(path: &str) -> Result<World, String> {
+    Ok(load_scene(path)?.world)
+}
+
+fn parse_camera(value: &Value) -> Result<CameraBuilder, String> {
+    let mut camera = CameraBuilder::new()
+        .lookfrom(parse_vec3(field(value, "look_from")?)?)
+        .lookat(parse_vec3(field(value, "look_at")?)?)
+        .vup(parse_vec3(field(value, "vup")?)?)
+        .vfov(parse_f64(value, "vfov")?)
+        .aspect_ratio(parse_f64_or(value, "aspect", 16.0 / 9.0)?)
+        .aperture(parse_f64_or(value, "aperture", 0.0)?);
+    if value.get("focus_dist").is_some() {
+        camera = camera.focus_dist(parse_f64(value, "focus_dist")?);
+    }
+    if value.get("shutter_open").is_some() || value.get("shutter_close").is_some() {
+        camera = camera.shutter(
+            parse_f64_or(value, "shutter_open", 0.0)?,
+            parse_f64_or(value, "shutter_close", 0.0)?,
+        );
+    }
+    if let Some(projection) = value.get("projection") {
+        camera = camera.projection(parse_projection(projection)?);
+    }
+    if let Some(spectral) = value.get("spectral") {
+        camera = camera.spectral(spectral.as_bool().ok_or_else(|| "field \"spectral\" is not a boolean".to_string())?);
+    }
+    Ok(camera)
+}
+
+// "Perspective", "Equirectangular", { "type": "Orthographic", "view_width": 4 } or
+// { "type": "Fisheye", "fov": 180 }
+fn parse_projection(value: &Value) -> Result<ProjectionKind, String> {
+    let kind = match value.as_str() {
+        Some(kind) => kind,
+        None => type_of(value)?,
+    };
+    match kind {
+        "Perspective" => Ok(ProjectionKind::Perspective),
+        "Equirectangular" => Ok(ProjectionKind::Equirectangular),
+        "Orthographic" => Ok(ProjectionKind::Orthographic { view_width: parse_f64(value, "view_width")? }),
+        "Fisheye" => Ok(ProjectionKind::Fisheye { fov: parse_f64_or(value, "fov", 180.0)? }),
+        other => Err(format!("unsupported projection \"{}\"", other)),
+    }
+}
+
+// { "interpolation": "CatmullRom", "start_frame": 0, "end_frame": 47,
+//   "keyframes": [{ "frame": 0, "look_from": {..}, "look_at": {..}, "vfov": 40 }, ...] }
+// The frame range is inclusive and defaults to the frames of the first and last keyframe.
+fn parse_animation(value: &Value, vup: Vec3) -> Result<Animation, String> {
+    let interpolation = match value.get("interpolation").and_then(Value::as_str) {
+        None | Some("Linear") => Interpolation::Linear,
+        Some("CatmullRom") => Interpolation::CatmullRom,
+        Some(other) => return Err(format!("unsupported interpolation \"{}\"", other)),
+    };
+    let mut keyframes = vec![];
+    for keyframe in field(value, "keyframes")?
+        .as_array()
+        .ok_or_else(|| "field \"keyframes\" is not an array".to_string())?
+    {
+        keyframes.push(CameraKeyframe {
+            frame: parse_f64(keyframe, "frame")?,
+            lookfrom: parse_vec3(field(keyframe, "look_from")?)?,
+            lookat: parse_vec3(field(keyframe, "look_at")?)?,
+            vfov: parse_f64(keyframe, "vfov")?,
+        });
+    }
+    if keyframes.is_empty() {
+        return Err("a camera path needs at least one keyframe".to_string());
+    }
+    let path = CameraPath::new(keyframes, interpolation);
+    let first = path.keyframes()[0].frame;
+    let last = path.keyframes()[path.keyframes().len() - 1].frame;
+    let start_frame = parse_f64_or(value, "start_frame", first)?;
+    let end_frame = parse_f64_or(value, "end_frame", last)?;
+    if start_frame < 0.0 || end_frame < start_frame {
+        return Err(format!("invalid frame range {} to {}", start_frame, end_frame));
+    }
+    Ok(Animation { path, vup, frames: start_frame as u32..end_frame as u32 + 1 })
+}
+
+fn field<'a>(value: &'a Value, key: &str) -> Result<&'a Value, String> {
+    value.get(key).ok_or_else(|| format!("missing field \"{}\"", key))
+}
+
+fn type_of(value: &Value) -> Result<&str, String> {
+    field(value, "type")?
+        .as_str()
+        .ok_or_else(|| "field \"type\" is not a string".to_string())
+}
+
+fn parse_f64(value: &Value, key: &str) -> Result<f64, String> {
+    field(value, key)?
+        .as_f64()
+        .ok_or_else(|| format!("field \"{}\" is not a number", key))
+}
+
+fn parse_f64_or(value: &Value, key: &str, default: f64) -> Result<f64, String> {
+    match value.get(key) {
+        Some(_) => parse_f64(value, key),
+        None => Ok(default),
+    }
+}
+
+fn parse_f64_array3(value: &Value, key: &str) -> Result<[f64; 3], String> {
+    let items = field(value, key)?
+        .as_array()
+        .filter(|items| items.len() == 3)
+        .ok_or_else(|| format!("field \"{}\" is not an array of 3 numbers", key))?;
+    let mut array = [0.0; 3];
+    for (a, item) in array.iter_mut().zip(items) {
+        *a = item.as_f64().ok_or_else(|| format!("field \"{}\" is not an array of 3 numbers", key))?;
+    }
+    Ok(array)
+}
+
+fn parse_vec3(value: &Value) -> Result<Vec3, String> {
+    Ok(Vec3::new(
+        parse_f64(value, "x")?,
+        parse_f64(value, "y")?,
+        parse_f64(value, "z")?,
+    ))
+}
+
+// a color is either a plain vector or a constant texture
+fn parse_color(value: &Value) -> Result<Color, String> {
+    match value.get("type").and_then(Value::as_str) {
+        None => parse_vec3(value),
+        Some("ConstantTexture") => parse_vec3(field(value, "color")?),
+        Some(other) => Err(format!("unsupported texture \"{}\"", other)),
+    }
+}
+
+// checker patterns default to the frequency used by the scenes in `data/`
+const CHECKER_SCALE: f64 = 10.0;
+
+fn parse_texture(value: &Value) -> Result<Box<dyn Texture>, String> {
+    match value.get("type").and_then(Value::as_str) {
+        None | Some("ConstantTexture") => Ok(Box::new(ConstantTexture::new(parse_color(value)?))),
+        Some("CheckerTexture") => Ok(Box::new(CheckerTexture::new(
+            parse_texture(field(value, "t0")?)?,
+            parse_texture(field(value, "t1")?)?,
+            parse_f64_or(value, "scale", CHECKER_SCALE)?,
+        ))),
+        Some(other) => Err(format!("unsupported texture \"{}\"", other)),
+    }
+}
+
+// Materials only take plain colors, so a checker textured material is built as
+// a mix of the materials of both cells weighted by a black and white checker.
+fn parse_textured_material(
+    value: &Value,
+    new_material: &dyn Fn(Color) -> Box<dyn Material>,
+) -> Result<Box<dyn Material>, String> {
+    match value.get("type").and_then(Value::as_str) {
+        Some("CheckerTexture") => Ok(Box::new(Mix::new_textured(
+            parse_textured_material(field(value, "t0")?, new_material)?,
+            parse_textured_material(field(value, "t1")?, new_material)?,
+            CheckerTexture::new(
+                ConstantTexture::new(Color::zero()),
+                ConstantTexture::new(Color::ones()),
+                parse_f64_or(value, "scale", CHECKER_SCALE)?,
+            ),
+        ))),
+        _ => Ok(new_material(parse_color(value)?)),
+    }
+}
+
+// "BK7", "SF11", { "type": "Cauchy", "a": 1.5, "b": 0.004 } or
+// { "type": "Sellmeier", "b": [b1, b2, b3], "c": [c1, c2, c3] }, wavelengths in micrometers
+fn parse_dispersion(value: &Value) -> Result<Dispersion, String> {
+    match value.as_str() {
+        Some("BK7") => return Ok(Dispersion::bk7()),
+        Some("SF11") => return Ok(Dispersion::sf11()),
+        Some(other) => return Err(format!("unknown glass \"{}\"", other)),
+        None => {}
+    }
+    match type_of(value)? {
+        "Cauchy" => Ok(Dispersion::Cauchy { a: parse_f64(value, "a")?, b: parse_f64(value, "b")? }),
+        "Sellmeier" => Ok(Dispersion::Sellmeier { b: parse_f64_array3(value, "b")?, c: parse_f64_array3(value, "c")? }),
+        other => Err(format!("unsupported dispersion \"{}\"", other)),
+    }
+}
+
+// the objects with their material IDs, 0 for a material of their own
+fn parse_hittable(value: &Value, materials: &Map<String, Value>, hittables: &mut Vec<(Box<dyn Hittable>, u32)>) -> Result<(), String> {
+    match type_of(value)? {
+        "HitableList" => {
+            let items = field(value, "items")?
+                .as_array()
+                .ok_or_else(|| "field \"items\" is not an array".to_string())?;
+            for item in items {
+                parse_hittable(item, materials, hittables)?;
+            }
+        }
+        // the tree is rebuilt by the camera, so only its leaves are kept
+        "BVHNode" => {
+            parse_hittable(field(value, "left")?, materials, hittables)?;
+            parse_hittable(field(value, "right")?, materials, hittables)?;
+        }
+        "Sphere" => {
+            let center = parse_vec3(field(value, "center")?)?;
+            let radius = parse_f64(value, "radius")?;
+            let (material, material_id) = parse_object_material(field(value, "material")?, materials)?;
+            hittables.push((Box::new(Sphere::new(center, radius, material)), material_id));
+        }
+        // parallelogram with corner q and edges u and v
+        "Quad" => {
+            let q = parse_vec3(field(value, "q")?)?;
+            let u = parse_vec3(field(value, "u")?)?;
+            let v = parse_vec3(field(value, "v")?)?;
+            let (material, material_id) = parse_object_material(field(value, "material")?, materials)?;
+            hittables.push((Box::new(Quad::new(q, u, v, material)), material_id));
+        }
+        "ConstantMedium" => {
+            let (boundary, _) = parse_single_hittable(field(value, "boundary")?, materials)?;
+            let density = parse_f64(value, "density")?;
+            let albedo = parse_color(field(value, "albedo")?)?;
+            hittables.push((Box::new(ConstantMedium::new(boundary, density, albedo)), 0));
+        }
+        // { "type": "GridMedium", "path": "smoke.nrrd", "min": {..}, "max": {..}, "density_scale": 10,
+        //   "albedo": {..}, "g": 0.3, "emission": {..}, "emission_path": "temperature.nrrd" }
+        "GridMedium" => {
+            let path = field(value, "path")?
+                .as_str()
+                .ok_or_else(|| "field \"path\" is not a string".to_string())?;
+            let mut medium = GridMedium::new(
+                VoxelGrid::load(path)?,
+                parse_vec3(field(value, "min")?)?,
+                parse_vec3(field(value, "max")?)?,
+                parse_f64_or(value, "density_scale", 1.0)?,
+                parse_color(field(value, "albedo")?)?,
+                parse_f64_or(value, "g", 0.0)?,
+            );
+            if let Some(emission) = value.get("emission") {
+                let emission_grid = match value.get("emission_path").and_then(Value::as_str) {
+                    Some(path) => Some(VoxelGrid::load(path)?),
+                    None => None,
+                };
+                medium.set_emission(parse_color(emission)?, emission_grid);
+            }
+            hittables.push((Box::new(medium), 0));
+        }
+        // { "type": "Moving", "object": { ... }, "keyframes": [{ "time": 0, "offset": { .. } }, ...] }
+        "Moving" => {
+            let (object, material_id) = parse_single_hittable(field(value, "object")?, materials)?;
+            let mut keyframes = vec![];
+            for keyframe in field(value, "keyframes")?
+                .as_array()
+                .ok_or_else(|| "field \"keyframes\" is not an array".to_string())?
+            {
+                keyframes.push((parse_f64(keyframe, "time")?, parse_vec3(field(keyframe, "offset")?)?));
+            }
+            if keyframes.is_empty() {
+                return Err("a moving object needs at least one keyframe".to_string());
+            }
+            hittables.push((Box::new(Moving::new(object, keyframes)), material_id));
+        }
+        other => return Err(format!("unsupported object \"{}\"", other)),
+    }
+    Ok(())
+}
+
+fn parse_single_hittable(value: &Value, materials: &Map<String, Value>) -> Result<(Box<dyn Hittable>, u32), String> {
+    let mut hittables = vec![];
+    parse_hittable(value, materials, &mut hittables)?;
+    if hittables.len() != 1 {
+        return Err(format!("expected a single object, got {}", hittables.len()));
+    }
+    Ok(hittables.pop().unwrap())
+}
+
+// an inline material, or the name of one in the materials table, whose entries are
+// numbered from 1
+fn parse_object_material(value: &Value, materials: &Map<String, Value>) -> Result<(Box<dyn Material>, u32), String> {
+    let name = match value.as_str() {
+        Some(name) => name,
+        None => return Ok((parse_material(value)?, 0)),
+    };
+    match materials.iter().position(|(key, _)| key == name) {
+        Some(index) => Ok((parse_material(&materials[name])?, index as u32 + 1)),
+        None => Err(format!("unknown material \"{}\"", name)),
+    }
+}
+
+fn parse_material(value: &Value) -> Result<Box<dyn Material>, String> {
+    let material: Box<dyn Material> = match type_of(value)? {
+        "Lambertian" => parse_textured_material(field(value, "albedo")?, &|albedo| Box::new(Diffusive::new(albedo)))?,
+        "Metal" => Box::new(Metal::new(
+            parse_color(field(value, "albedo")?)?,
+            parse_f64_or(value, "fuzz", 0.0)?,
+        )),
+        // dispersion only shows when the camera is spectral, ref_idx is then taken from it
+        "Dielectric" => match value.get("dispersion") {
+            Some(dispersion) => Box::new(Dieletric::new_dispersive(Color::ones(), parse_dispersion(dispersion)?)),
+            None => Box::new(Dieletric::new(Color::ones(), parse_f64(value, "ref_idx")?)),
+        },
+        "DiffuseLight" => parse_textured_material(field(value, "emit")?, &|emit| Box::new(Light::new(emit)))?,
+        "Mix" => {
+            let a = parse_material(field(value, "a")?)?;
+            let b = parse_material(field(value, "b")?)?;
+            let weight = field(value, "weight")?;
+            match weight.as_f64() {
+                Some(weight) => Box::new(Mix::new(a, b, weight)),
+                None => Box::new(Mix::new_textured(a, b, parse_texture(weight)?)),
+            }
+        }
+        "Coated" => {
+            let mut coated = Coated::new(
+                parse_material(field(value, "base")?)?,
+                parse_f64_or(value, "ior", 1.5)?,
+                parse_f64_or(value, "roughness", 0.0)?,
+            );
+            if let Some(tint) = value.get("tint") {
+                coated.tint = parse_color(tint)?;
+            }
+            Box::new(coated)
+        }
+        "Principled" => {
+            let default = Principled::new(Color::new(0.8, 0.8, 0.8));
+            let base_color = match value.get("base_color") {
+                Some(color) => parse_color(color)?,
+                None => default.base_color,
+            };
+            Box::new(Principled {
+                base_color,
+                metallic: parse_f64_or(value, "metallic", default.metallic)?,
+                roughness: parse_f64_or(value, "roughness", default.roughness)?,
+                specular: parse_f64_or(value, "specular", default.specular)?,
+                specular_tint: parse_f64_or(value, "specular_tint", default.specular_tint)?,
+                sheen: parse_f64_or(value, "sheen", default.sheen)?,
+                sheen_tint: parse_f64_or(value, "sheen_tint", default.sheen_tint)?,
+                clearcoat: parse_f64_or(value, "clearcoat", default.clearcoat)?,
+                clearcoat_gloss: parse_f64_or(value, "clearcoat_gloss", default.clearcoat_gloss)?,
+                transmission: parse_f64_or(value, "transmission", default.transmission)?,
+                ior: parse_f64_or(value, "ior", default.ior)?,
+                subsurface: parse_f64_or(value, "subsurface", default.subsurface)?,
+            })
+        }
+        other => return Err(format!("unsupported material \"{}\"", other)),
+    };
+    Ok(material)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sphere(material: &str) -> String {
+        format!(r#"{{ "type": "Sphere", "center": {{ "x": 0, "y": 0, "z": -1 }}, "radius": 0.5, "material": {} }}"#, material)
+    }
+
+    #[test]
+    fn test_parse_scene() {
+        let materials = [
+            r#"{ "type": "Lambertian", "albedo": { "type": "CheckerTexture", "t0": { "x": 0, "y": 0, "z": 0 }, "t1": { "x": 1, "y": 1, "z": 1 } } }"#,
+            r#"{ "type": "Metal", "albedo": { "x": 0.8, "y": 0.8, "z": 0.8 }, "fuzz": 0.1 }"#,
+            r#"{ "type": "Dielectric", "ref_idx": 1.5 }"#,
+            r#"{ "type": "Dielectric", "dispersion": "BK7" }"#,
+            r#"{ "type": "DiffuseLight", "emit": { "type": "ConstantTexture", "color": { "x": 4, "y": 4, "z": 4 } } }"#,
+            r#"{ "type": "Mix", "a": { "type": "Dielectric", "ref_idx": 1.5 }, "b": { "type": "Metal", "albedo": { "x": 1, "y": 1, "z": 1 } }, "weight": 0.3 }"#,
+            r#"{ "type": "Coated", "base": { "type": "Lambertian", "albedo": { "x": 0.5, "y": 0.1, "z": 0.1 } }, "roughness": 0.05 }"#,
+            r#"{ "type": "Principled", "base_color": { "x": 0.9, "y": 0.6, "z": 0.2 }, "metallic": 1, "roughness": 0.3 }"#,
+        ];
+        let mut items: Vec<String> = materials.iter().map(|m| sphere(m)).collect();
+        items.push(
+            r#"{ "type": "Quad", "q": { "x": -1, "y": 1, "z": -2 }, "u": { "x": 2, "y": 0, "z": 0 }, "v": { "x": 0, "y": 0, "z": 1 },
+                 "material": { "type": "DiffuseLight", "emit": { "x": 2, "y": 2, "z": 2 } } }"#
+                .to_string(),
+        );
+        let text = format!(
+            r#"{{ "camera": {{ "look_from": {{ "x": 0, "y": 0, "z": 1 }}, "look_at": {{ "x": 0, "y": 0, "z": -1 }},
+                               "vup": {{ "x": 0, "y": 1, "z": 0 }}, "vfov": 40, "spectral": true,
+                               "projection": {{ "type": "Fisheye", "fov": 150 }} }},
+                 "objects": {{ "type": "HitableList", "items": [{}] }} }}"#,
+            items.join(", ")
+        );
+        let scene = parse_scene(&text).unwrap();
+        assert_eq!(scene.world.hittables.len(), materials.len() + 1);
+        assert!(scene.animation.is_none());
+        let camera = scene.camera.unwrap().image_width(16).build(scene.world).unwrap();
+        assert!(camera.spectral);
+        assert!((camera.direction - Vec3::new(0.0, 0.0, -1.0)).length() < 1e-12);
+    }
+
+    #[test]
+    fn test_scene_errors() {
+        let objects = |object: &str| format!(r#"{{ "objects": {} }}"#, object);
+        let error = |text: String| parse_scene(&text).err().unwrap();
+        assert_eq!(error(objects(r#"{ "type": "Torus" }"#)), "unsupported object \"Torus\"");
+        assert_eq!(error(objects(&sphere(r#"{ "type": "Velvet" }"#))), "unsupported material \"Velvet\"");
+        let no_radius = r#"{ "type": "Sphere", "center": { "x": 0, "y": 0, "z": 0 }, "material": { "type": "Dielectric", "ref_idx": 1.5 } }"#;
+        assert_eq!(error(objects(no_radius)), "missing field \"radius\"");
+        assert_eq!(error(objects(&sphere(r#"{ "type": "Metal" }"#))), "missing field \"albedo\"");
+        assert_eq!(error(r#"{ "fog": {} }"#.to_string()), "missing field \"objects\"");
+        let camera = |projection: &str| {
+            format!(
+                r#"{{ "objects": {{ "type": "HitableList", "items": [] }}, "camera": {{ "look_from": {{ "x": 0, "y": 0, "z": 0 }},
+                     "look_at": {{ "x": 0, "y": 0, "z": -1 }}, "vup": {{ "x": 0, "y": 1, "z": 0 }}, "vfov": 90, "projection": {} }} }}"#,
+                projection
+            )
+        };
+        assert_eq!(error(camera(r#""Cylindrical""#)), "unsupported projection \"Cylindrical\"");
+        assert_eq!(error(camera(r#"{ "type": "Orthographic" }"#)), "missing field \"view_width\"");
+        assert!(parse_scene(&camera(r#""Equirectangular""#)).is_ok());
+        assert!(error("{ not json".to_string()).starts_with("failed to parse"));
+    }
+    #[test]
+    fn test_shared_materials() {
+        let text = format!(
+            r#"{{ "materials": {{ "gold": {{ "type": "Metal", "albedo": {{ "x": 0.8, "y": 0.6, "z": 0.2 }} }},
+                                 "lamp": {{ "type": "DiffuseLight", "emit": {{ "x": 4, "y": 4, "z": 4 }} }} }},
+                 "objects": {{ "type": "HitableList", "items": [{}, {}, {}, {}] }} }}"#,
+            sphere(r#""lamp""#),
+            sphere(r#""gold""#),
+            sphere(r#"{ "type": "Metal", "albedo": { "x": 0.8, "y": 0.6, "z": 0.2 } }"#),
+            sphere(r#""lamp""#)
+        );
+        let world = parse_scene(&text).unwrap().world;
+        assert_eq!(world.hittables.len(), 4);
+        assert_eq!(world.material_ids, vec![2, 1, 0, 2]);
+        let unknown = format!(r#"{{ "objects": {} }}"#, sphere(r#""silver""#));
+        assert_eq!(parse_scene(&unknown).err().unwrap(), "unknown material \"silver\"");
+    }
+}
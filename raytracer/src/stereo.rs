@@ -0,0 +1,218 @@
+use std::sync::Arc;
+
+use crate::camera::Camera;
+use crate::hdr::HdrImage;
+use crate::projection::ProjectionKind;
+use crate::util::ray::Ray;
+use crate::util::vec3::Vec3;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum Eye {
+    Left,
+    Right,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum StereoLayout {
+    SideBySide, // left eye on the left
+    TopBottom,  // left eye on top
+}
+
+impl StereoLayout {
+    pub const ALL: [StereoLayout; 2] = [StereoLayout::SideBySide, StereoLayout::TopBottom];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            StereoLayout::SideBySide => "side-by-side",
+            StereoLayout::TopBottom => "top-bottom",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Result<Self, String> {
+        Self::ALL
+            .iter()
+            .find(|layout| layout.name() == name)
+            .copied()
+            .ok_or_else(|| format!("unknown stereo layout: {}", name))
+    }
+}
+
+// Two eyes separated by the interpupillary distance along the right axis of the camera.
+#[derive(Clone, Copy, Debug)]
+pub struct StereoRig {
+    pub ipd: f64,
+    // distance of the zero parallax plane, f64::INFINITY for parallel eyes
+    pub convergence: f64,
+    pub layout: StereoLayout,
+    // Omni-directional stereo for equirectangular panoramas: each ray is shifted
+    // perpendicular to its own direction, so the stereo effect holds all around.
+    pub omnidirectional: bool,
+}
+
+impl StereoRig {
+    pub fn new(ipd: f64, convergence: f64, layout: StereoLayout) -> Self {
+        Self { ipd, convergence, layout, omnidirectional: false }
+    }
+
+    pub fn new_omnidirectional(ipd: f64, layout: StereoLayout) -> Self {
+        Self { ipd, convergence: f64::INFINITY, layout, omnidirectional: true }
+    }
+
+    // omni-directional stereo only makes sense for panoramas, and the eyes of a panorama
+    // need to turn with the rays
+    pub fn check_projection(&self, projection: ProjectionKind) -> Result<(), String> {
+        let panorama = projection == ProjectionKind::Equirectangular;
+        match (self.omnidirectional, panorama) {
+            (true, false) => Err("omni-directional stereo needs an equirectangular projection".to_string()),
+            (false, true) => Err("equirectangular panoramas need omni-directional stereo".to_string()),
+            _ => Ok(()),
+        }
+    }
+
+    // move a primary ray of the center camera to the given eye
+    pub fn offset_ray(&self, eye: Eye, camera: &Camera, ray: Ray) -> Ray {
+        let half_ipd = match eye {
+            Eye::Left => -0.5 * self.ipd,
+            Eye::Right => 0.5 * self.ipd,
+        };
+        let mut eye_ray = ray.clone();
+        if self.omnidirectional {
+            let up = -camera.v_unit;
+            let right = Vec3::cross(&ray.dir, &up);
+            if !right.near_zero() {
+                eye_ray.ori += right.unit() * half_ipd;
+            }
+            return eye_ray;
+        }
+
+        eye_ray.ori += camera.u_unit * half_ipd;
+        let depth = Vec3::dot(&ray.dir, &camera.direction);
+        if self.convergence.is_finite() && depth > 0.0 {
+            // both eyes look through the same point of the convergence plane
+            let target = ray.at(self.convergence / depth);
+            eye_ray.dir = (target - eye_ray.ori).unit();
+        }
+        eye_ray
+    }
+}
+
+// Render both eyes with the given render function and pack them into one image.
+// The camera must not be shared while rendering, since its eye is switched in between.
+pub fn render_stereo<F>(camera: &mut Arc<Camera>, rig: StereoRig, render: F) -> HdrImage
+where
+    F: Fn(&Arc<Camera>) -> HdrImage,
+{
+    Arc::get_mut(camera).expect("camera is shared").stereo = Some((rig, Eye::Left));
+    let left = render(camera);
+    Arc::get_mut(camera).expect("camera is shared").stereo = Some((rig, Eye::Right));
+    let right = render(camera);
+    Arc::get_mut(camera).expect("camera is shared").stereo = None;
+
+    let (width, height) = (left.width, left.height);
+    match rig.layout {
+        StereoLayout::SideBySide => HdrImage::from_fn(width * 2, height, |i, j| {
+            if i < width {
+                left.get(i, j)
+            } else {
+                right.get(i - width, j)
+            }
+        }),
+        StereoLayout::TopBottom => HdrImage::from_fn(width, height * 2, |i, j| {
+            if j < height {
+                left.get(i, j)
+            } else {
+                right.get(i, j - height)
+            }
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::camera::CameraBuilder;
+    use crate::hittable::sphere::Sphere;
+    use crate::material::diffusive::Diffusive;
+    use crate::util::vec3::{Color, Point3};
+    use crate::world::World;
+
+    // at the origin looking down -z, so u is +x
+    fn camera() -> Arc<Camera> {
+        let sphere = Sphere::new(Point3::new(0.0, 0.0, -5.0), 1.0, Diffusive::new(Color::ones()));
+        let world = World { hittables: vec![Box::new(sphere)], material_ids: vec![], fog: None };
+        Arc::new(CameraBuilder::new().image_width(8).aspect_ratio(2.0).build(world).unwrap())
+    }
+
+    #[test]
+    fn test_eyes_are_ipd_apart() {
+        let camera = camera();
+        let ray = Ray::new(Point3::zero(), Vec3::new(0.2, 0.1, -1.0).unit());
+        let parallel = StereoRig::new(0.06, f64::INFINITY, StereoLayout::SideBySide);
+        let left = parallel.offset_ray(Eye::Left, &camera, ray.clone());
+        let right = parallel.offset_ray(Eye::Right, &camera, ray.clone());
+        assert!((left.ori - Point3::new(-0.03, 0.0, 0.0)).length() < 1e-12);
+        assert!((right.ori - Point3::new(0.03, 0.0, 0.0)).length() < 1e-12);
+        assert_eq!(left.dir, ray.dir);
+
+        // converged eyes meet on the plane 2 units in front of the camera
+        let converged = StereoRig::new(0.06, 2.0, StereoLayout::SideBySide);
+        let left = converged.offset_ray(Eye::Left, &camera, ray.clone());
+        let right = converged.offset_ray(Eye::Right, &camera, ray.clone());
+        let on_plane = |ray: &Ray| ray.at(-2.0 / ray.dir.z);
+        assert!((on_plane(&left) - on_plane(&right)).length() < 1e-12);
+        assert!((on_plane(&left) - ray.at(2.0 / -ray.dir.z)).length() < 1e-12);
+    }
+
+    #[test]
+    fn test_omnidirectional_eyes_lie_on_a_circle() {
+        let camera = camera();
+        let rig = StereoRig::new_omnidirectional(0.06, StereoLayout::TopBottom);
+        for k in 0..16 {
+            let angle = k as f64 * std::f64::consts::PI / 8.0;
+            let ray = Ray::new(Point3::zero(), Vec3::new(angle.sin(), 0.0, -angle.cos()));
+            for &eye in &[Eye::Left, Eye::Right] {
+                let eye_ray = rig.offset_ray(eye, &camera, ray.clone());
+                // on the circle of radius ipd / 2 and looking along its tangent
+                assert!((eye_ray.ori.length() - 0.03).abs() < 1e-12);
+                assert!(Vec3::dot(&eye_ray.ori, &eye_ray.dir).abs() < 1e-12);
+            }
+            let left = rig.offset_ray(Eye::Left, &camera, ray.clone()).ori;
+            let right = rig.offset_ray(Eye::Right, &camera, ray.clone()).ori;
+            assert!((left + right).length() < 1e-12);
+        }
+    }
+
+    #[test]
+    fn test_layouts() {
+        // a flat image per eye, red for the left one
+        let render = |camera: &Arc<Camera>| {
+            let color = match camera.stereo {
+                Some((_, Eye::Left)) => Color::new(1.0, 0.0, 0.0),
+                _ => Color::new(0.0, 0.0, 1.0),
+            };
+            HdrImage::from_fn(camera.image_width, camera.image_height, |_, _| color)
+        };
+        let mut camera = camera();
+        let rig = StereoRig::new(0.06, f64::INFINITY, StereoLayout::SideBySide);
+        let image = render_stereo(&mut camera, rig, render);
+        assert_eq!((image.width, image.height), (16, 4));
+        assert_eq!((image.get(7, 3).x, image.get(8, 0).z), (1.0, 1.0));
+
+        let rig = StereoRig { layout: StereoLayout::TopBottom, ..rig };
+        let image = render_stereo(&mut camera, rig, render);
+        assert_eq!((image.width, image.height), (8, 8));
+        assert_eq!((image.get(7, 3).x, image.get(0, 4).z), (1.0, 1.0));
+        assert!(camera.stereo.is_none());
+        assert_eq!(StereoLayout::from_name("top-bottom"), Ok(StereoLayout::TopBottom));
+    }
+
+    #[test]
+    fn test_projection_check() {
+        let ods = StereoRig::new_omnidirectional(0.06, StereoLayout::TopBottom);
+        let parallel = StereoRig::new(0.06, f64::INFINITY, StereoLayout::SideBySide);
+        assert!(ods.check_projection(ProjectionKind::Equirectangular).is_ok());
+        assert!(ods.check_projection(ProjectionKind::Perspective).is_err());
+        assert!(parallel.check_projection(ProjectionKind::Fisheye { fov: 180.0 }).is_ok());
+        assert!(parallel.check_projection(ProjectionKind::Equirectangular).is_err());
+    }
+}
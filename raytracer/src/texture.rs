@@ -0,0 +1,14 @@
+pub mod constant;
+pub mod checker;
+
+use crate::util::vec3::{Color, Point3};
+
+pub trait Texture: Send + Sync {
+    fn value(&self, point: &Point3) -> Color;
+}
+
+impl<T: Texture + ?Sized> Texture for Box<T> {
+    fn value(&self, point: &Point3) -> Color {
+        (**self).value(point)
+    }
+}
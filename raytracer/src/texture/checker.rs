@@ -0,0 +1,48 @@
+use super::Texture;
+use crate::util::vec3::{Color, Point3};
+
+// solid checker pattern, even cells take t0 and odd cells take t1
+#[derive(Clone, Copy)]
+pub struct CheckerTexture<T0: Texture, T1: Texture> {
+    pub t0: T0,
+    pub t1: T1,
+    pub scale: f64, // spatial frequency of the pattern
+}
+
+impl<T0: Texture, T1: Texture> CheckerTexture<T0, T1> {
+    pub fn new(t0: T0, t1: T1, scale: f64) -> Self {
+        Self { t0, t1, scale }
+    }
+}
+
+impl<T0: Texture, T1: Texture> Texture for CheckerTexture<T0, T1> {
+    fn value(&self, point: &Point3) -> Color {
+        let sines = (self.scale * point.x).sin() * (self.scale * point.y).sin() * (self.scale * point.z).sin();
+        if sines < 0.0 {
+            self.t1.value(point)
+        } else {
+            self.t0.value(point)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::texture::constant::ConstantTexture;
+
+    #[test]
+    fn test_checker_cells() {
+        let (black, white) = (ConstantTexture::new(Color::zero()), ConstantTexture::new(Color::ones()));
+        let checker = CheckerTexture::new(black, white, 2.0);
+        // cells are pi / scale wide and flip in every direction
+        let at = |x: f64, y: f64, z: f64| checker.value(&Point3::new(x, y, z));
+        assert_eq!(at(0.5, 0.5, 0.5), Color::zero());
+        assert_eq!(at(2.0, 0.5, 0.5), Color::ones());
+        assert_eq!(at(2.0, 2.0, 0.5), Color::zero());
+        assert_eq!(at(-0.5, 0.5, 0.5), Color::ones());
+        // the scale sets the cell size
+        let fine = CheckerTexture::new(black, white, 20.0);
+        assert_eq!(fine.value(&Point3::new(0.2, 0.05, 0.05)), Color::ones());
+    }
+}
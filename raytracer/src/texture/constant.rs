@@ -0,0 +1,19 @@
+use super::Texture;
+use crate::util::vec3::{Color, Point3};
+
+#[derive(Clone, Copy)]
+pub struct ConstantTexture {
+    pub color: Color,
+}
+
+impl ConstantTexture {
+    pub fn new(color: Color) -> Self {
+        Self { color }
+    }
+}
+
+impl Texture for ConstantTexture {
+    fn value(&self, _point: &Point3) -> Color {
+        self.color
+    }
+}
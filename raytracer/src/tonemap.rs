@@ -0,0 +1,185 @@
+// Turning scene referred radiance into display values: exposure, a tone mapping operator that
+// rolls highlights off into [0, 1] instead of clipping them, and the sRGB transfer curve.
+
+use image::Rgb;
+
+use crate::film::luminance;
+use crate::util::vec3::Color;
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum ToneOperator {
+    // clip at 1, the look of the renderer before tone mapping existed
+    Clamp,
+    // L / (1 + L) on the luminance, keeps the hue
+    Reinhard,
+    // Stephen Hill's fit of the ACES reference rendering and sRGB output transforms
+    Aces,
+    // AgX base look (minimal approximation by Benjamin Wrensch), desaturates bright colors
+    // towards white like film does instead of skewing their hue
+    Agx,
+}
+
+impl ToneOperator {
+    pub const ALL: [ToneOperator; 4] = [ToneOperator::Clamp, ToneOperator::Reinhard, ToneOperator::Aces, ToneOperator::Agx];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            ToneOperator::Clamp => "none",
+            ToneOperator::Reinhard => "reinhard",
+            ToneOperator::Aces => "aces",
+            ToneOperator::Agx => "agx",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Result<Self, String> {
+        Self::ALL
+            .iter()
+            .find(|operator| operator.name() == name)
+            .copied()
+            .ok_or_else(|| format!("unknown tone mapping operator: {}", name))
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct ToneMapping {
+    pub operator: ToneOperator,
+    pub exposure: f64, // in stops, every +1 doubles the radiance
+}
+
+impl Default for ToneMapping {
+    fn default() -> Self {
+        Self { operator: ToneOperator::Clamp, exposure: 0.0 }
+    }
+}
+
+impl ToneMapping {
+    // linear radiance to linear display values in [0, 1]
+    pub fn apply(&self, color: Color) -> Color {
+        let color = color * 2f64.powf(self.exposure);
+        let color = Color::new(color.x.max(0.0), color.y.max(0.0), color.z.max(0.0));
+        let mapped = match self.operator {
+            ToneOperator::Clamp => color,
+            ToneOperator::Reinhard => {
+                let l = luminance(color);
+                if l > 0.0 {
+                    color / (1.0 + l)
+                } else {
+                    color
+                }
+            }
+            ToneOperator::Aces => aces(color),
+            ToneOperator::Agx => agx(color),
+        };
+        Color::new(clamp01(mapped.x), clamp01(mapped.y), clamp01(mapped.z))
+    }
+
+    pub fn to_rgb(&self, color: Color) -> Rgb<u8> {
+        let display = self.apply(color);
+        Rgb([quantize(srgb_oetf(display.x)), quantize(srgb_oetf(display.y)), quantize(srgb_oetf(display.z))])
+    }
+}
+
+// NaN becomes 0 as well
+fn clamp01(x: f64) -> f64 {
+    x.max(0.0).min(1.0)
+}
+
+// nearest 8 bit value of x in [0, 1], anything outside is clipped
+pub fn quantize(x: f64) -> u8 {
+    (clamp01(x) * 255.0 + 0.5) as u8
+}
+
+// the sRGB transfer curve, linear near black and a 2.4 power above
+pub fn srgb_oetf(x: f64) -> f64 {
+    if x <= 0.0031308 {
+        12.92 * x.max(0.0)
+    } else {
+        1.055 * x.powf(1.0 / 2.4) - 0.055
+    }
+}
+
+fn mul(m: &[[f64; 3]; 3], c: Color) -> Color {
+    Color::new(
+        m[0][0] * c.x + m[0][1] * c.y + m[0][2] * c.z,
+        m[1][0] * c.x + m[1][1] * c.y + m[1][2] * c.z,
+        m[2][0] * c.x + m[2][1] * c.y + m[2][2] * c.z,
+    )
+}
+
+fn map(c: Color, f: impl Fn(f64) -> f64) -> Color {
+    Color::new(f(c.x), f(c.y), f(c.z))
+}
+
+fn aces(color: Color) -> Color {
+    const INPUT: [[f64; 3]; 3] = [[0.59719, 0.35458, 0.04823], [0.07600, 0.90834, 0.01566], [0.02840, 0.13383, 0.83777]];
+    const OUTPUT: [[f64; 3]; 3] =
+        [[1.60475, -0.53108, -0.07367], [-0.10208, 1.10813, -0.00605], [-0.00327, -0.07276, 1.07602]];
+    let v = mul(&INPUT, color);
+    let v = map(v, |x| (x * (x + 0.0245786) - 0.000090537) / (x * (0.983729 * x + 0.4329510) + 0.238081));
+    mul(&OUTPUT, v)
+}
+
+fn agx(color: Color) -> Color {
+    const INSET: [[f64; 3]; 3] = [
+        [0.842479062253094, 0.0784335999999992, 0.0792237451477643],
+        [0.0423282422610123, 0.878468636469772, 0.0791661274605434],
+        [0.0423756549057051, 0.0784336, 0.879142973793104],
+    ];
+    const OUTSET: [[f64; 3]; 3] = [
+        [1.19687900512017, -0.0980208811401368, -0.0990297440797205],
+        [-0.0528968517574562, 1.15190312990417, -0.0989611768448433],
+        [-0.0529716355144438, -0.0980434501171241, 1.15107367264116],
+    ];
+    const MIN_EV: f64 = -12.47393;
+    const MAX_EV: f64 = 4.026069;
+    // log encode the inset color over the range the curve was made for, apply the sigmoid
+    let v = mul(&INSET, color);
+    let v = map(v, |x| (x.max(1e-10).log2().max(MIN_EV).min(MAX_EV) - MIN_EV) / (MAX_EV - MIN_EV));
+    let v = map(v, |x| {
+        let x2 = x * x;
+        let x4 = x2 * x2;
+        15.5 * x4 * x2 - 40.14 * x4 * x + 31.96 * x4 - 6.868 * x2 * x + 0.4298 * x2 + 0.1191 * x - 0.00232
+    });
+    // the curve output is display encoded, go back to linear so the sRGB curve applies as usual
+    map(mul(&OUTSET, v), |x| x.max(0.0).powf(2.2))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_srgb_and_quantize() {
+        assert_eq!(srgb_oetf(0.0), 0.0);
+        assert!((srgb_oetf(1.0) - 1.0).abs() < 1e-12);
+        // both pieces meet at the threshold
+        assert!((12.92 * 0.0031308 - (1.055 * 0.0031308f64.powf(1.0 / 2.4) - 0.055)).abs() < 1e-6);
+        assert!((srgb_oetf(0.18) - 0.4613).abs() < 1e-3);
+        assert_eq!((quantize(-0.5), quantize(0.5), quantize(1.5), quantize(f64::NAN)), (0, 128, 255, 0));
+    }
+
+    #[test]
+    fn test_operators_roll_off() {
+        for &operator in &ToneOperator::ALL {
+            let tone = ToneMapping { operator, exposure: 0.0 };
+            let mut last = -1.0;
+            for k in 0..200 {
+                let v = tone.apply(Color::ones() * (k as f64 * 0.1)).y;
+                assert!((0.0..=1.0).contains(&v) && v >= last - 1e-9, "{:?} at {}", operator, k);
+                last = v;
+            }
+            assert!(tone.apply(Color::zero()).y < 0.01);
+        }
+        // the light color 3.0 is no longer white with a curve that rolls off
+        let reinhard = ToneMapping { operator: ToneOperator::Reinhard, exposure: 0.0 };
+        assert!(reinhard.apply(Color::ones() * 3.0).x < 0.8);
+    }
+
+    #[test]
+    fn test_exposure() {
+        let tone = ToneMapping { operator: ToneOperator::Clamp, exposure: 1.0 };
+        assert_eq!(tone.apply(Color::ones() * 0.25), Color::ones() * 0.5);
+        let tone = ToneMapping { exposure: -2.0, ..tone };
+        assert_eq!(tone.apply(Color::ones() * 3.0), Color::ones() * 0.75);
+    }
+}
@@ -3,6 +3,10 @@ pub mod ray;
 pub mod interval;
 pub mod vec3;
 pub mod bvh;
+pub mod spectrum;
+pub mod onb;
+pub mod voxel_grid;
+pub mod sampler;
 
 
 // For debugging
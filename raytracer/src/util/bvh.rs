@@ -5,7 +5,7 @@ use crate::hittable::Hittable;
 use crate::hittable::HitRecord;
 use crate::world::World;
 
-use rand::Rng;
+use crate::util::sampler::{Independent, Sampler, DEFAULT_SEED};
 
 #[derive(Debug, Clone, Copy)]
 pub struct AABB {
@@ -56,6 +56,11 @@ impl AABB {
 
     // return true if the ray hit the AABB
     pub fn hit(&self, ray: &Ray, rot: &Interval) -> bool {
+        self.hit_interval(ray, rot).is_some()
+    }
+
+    // return the part of rot during which the ray is inside the AABB
+    pub fn hit_interval(&self, ray: &Ray, rot: &Interval) -> Option<Interval> {
         let mut ray_t_min = rot.tmin;
         let mut ray_t_max = rot.tmax; 
         for i in 0..3 {
@@ -68,11 +73,11 @@ impl AABB {
             ray_t_max = f64::min(ray_t_max, t_1);
             // println!("{}: {}", i, ray_t_max);
             if ray_t_max <= ray_t_min {
-                return false;
+                return None;
             }
         }
-        return true;
-    } 
+        Some(Interval::new(ray_t_min, ray_t_max))
+    }
 }
 
 
@@ -81,6 +86,8 @@ pub struct BVHNode {
     pub right: Option<Box<BVHNode>>,
     pub obj: Option<Box<dyn Hittable>>,
     pub bbox: AABB,
+    pub object_id: u32,   // of the leaf object, numbered from 1 in world order
+    pub material_id: u32, // of the leaf object, shared IDs of the world first, then one per object
 }
 
 impl BVHNode {
@@ -90,30 +97,55 @@ impl BVHNode {
             (None, None,Some(obj)) => obj.bbox(),
             _ => panic!("Invalid BVHNode"),
         };
-        Self { left, right, obj, bbox }
+        Self { left, right, obj, bbox, object_id: 0, material_id: 0 }
     }
 
     pub fn new_from_world(world: World) -> Self {
-        Self::new_from_vec(world.hittables)
+        let mut next_id = world.material_ids.iter().max().copied().unwrap_or(0);
+        let mut material_ids = world.material_ids;
+        material_ids.resize(world.hittables.len(), 0);
+        for id in material_ids.iter_mut().filter(|id| **id == 0) {
+            next_id += 1;
+            *id = next_id;
+        }
+        Self::new_with_material_ids(world.hittables, material_ids)
+    }
+
+    // every object with a material of its own
+    pub fn new_from_vec(hittables: Vec<Box<dyn Hittable>>) -> Self {
+        let material_ids = (1..=hittables.len() as u32).collect();
+        Self::new_with_material_ids(hittables, material_ids)
+    }
+
+    // the split axes are random but fixed, so the same scene always gets the same tree
+    fn new_with_material_ids(hittables: Vec<Box<dyn Hittable>>, material_ids: Vec<u32>) -> Self {
+        let hittables = (1..)
+            .zip(material_ids)
+            .zip(hittables)
+            .map(|((object_id, material_id), hittable)| (object_id, material_id, hittable))
+            .collect();
+        Self::new_from_vec_with(hittables, &mut Independent::new(DEFAULT_SEED))
     }
 
-    pub fn new_from_vec(mut hittables: Vec<Box<dyn Hittable>>) -> Self {
-        let mut rng = rand::thread_rng();
-        let axis = rng.gen_range(0..3);
+    fn new_from_vec_with(mut hittables: Vec<(u32, u32, Box<dyn Hittable>)>, sampler: &mut dyn Sampler) -> Self {
+        let axis = sampler.next_below(3) as usize;
         let length = hittables.len();
-        hittables.sort_by(|a, b| {
+        hittables.sort_by(|(_, _, a), (_, _, b)| {
             let a_bbox = a.bbox();
             let b_bbox = b.bbox();
             a_bbox.get_axis(axis).tmin.partial_cmp(&b_bbox.get_axis(axis).tmin).unwrap()
         });
         if length == 1 {
-            let hittable = hittables.pop().unwrap();
-            Self::new(None, None, Some(hittable))
+            let (object_id, material_id, hittable) = hittables.pop().unwrap();
+            let mut leaf = Self::new(None, None, Some(hittable));
+            leaf.object_id = object_id;
+            leaf.material_id = material_id;
+            leaf
         } else {
             let mut left_vec = hittables;
             let right_vec = left_vec.split_off(length / 2);
-            let left = Some(Box::new(Self::new_from_vec(left_vec)));
-            let right = Some(Box::new(Self::new_from_vec(right_vec)));
+            let left = Some(Box::new(Self::new_from_vec_with(left_vec, sampler)));
+            let right = Some(Box::new(Self::new_from_vec_with(right_vec, sampler)));
             // if let Some(l) = &left {
             //     println!("l: {} {:?}", length/2, l.bbox);
             // }
@@ -127,21 +159,25 @@ impl BVHNode {
 }
 
 impl Hittable for BVHNode {
-    fn hit(&self, ray: &Ray, rot: &Interval) -> Option<HitRecord> {
+    fn hit(&self, ray: &Ray, rot: &Interval, sampler: &mut dyn Sampler) -> Option<HitRecord> {
         if !self.bbox.hit(ray, rot) {
             return None;
         }
         match (&self.left, &self.right, &self.obj) {
             (None, None, Some(obj)) => {
-                return obj.hit(ray, rot);
+                return obj.hit(ray, rot, sampler).map(|record| HitRecord {
+                    object_id: self.object_id,
+                    material_id: self.material_id,
+                    ..record
+                });
             }
             (Some(l), Some(r), None) => {
-                let hit_record_l = l.hit(ray, rot);
+                let hit_record_l = l.hit(ray, rot, sampler);
                 let mut rot = rot.clone();
                 if let Some(record) = hit_record_l {
                     rot.set_tmax(record.t);
                 }
-                let hit_record_r = r.hit(ray, &rot);
+                let hit_record_r = r.hit(ray, &rot, sampler);
                 if let Some(record) = hit_record_r {
                     Some(record)
                 } else {
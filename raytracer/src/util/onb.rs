@@ -0,0 +1,28 @@
+use crate::util::vec3::Vec3;
+
+// orthonormal basis with w along a given direction
+#[derive(Clone, Copy, Debug)]
+pub struct ONB {
+    pub u: Vec3,
+    pub v: Vec3,
+    pub w: Vec3,
+}
+
+impl ONB {
+    pub fn new_from_w(w: Vec3) -> Self {
+        let w = w.unit();
+        let a = if w.x.abs() > 0.9 {
+            Vec3::new(0.0, 1.0, 0.0)
+        } else {
+            Vec3::new(1.0, 0.0, 0.0)
+        };
+        let v = Vec3::cross(&w, &a).unit();
+        let u = Vec3::cross(&w, &v);
+        Self { u, v, w }
+    }
+
+    // transform local coordinates into world space
+    pub fn local(&self, a: f64, b: f64, c: f64) -> Vec3 {
+        self.u * a + self.v * b + self.w * c
+    }
+}
@@ -6,11 +6,21 @@ pub struct Ray {
     pub ori: Point3,
     //direction, unitified
     pub dir: Vec3,
+    // sampled wavelength in nanometers, only set in spectral rendering mode
+    pub wavelength: Option<f64>,
+    // time within the shutter interval, used for motion blur
+    pub time: f64,
 }
 
 impl Ray {
     pub fn new(ori: Point3, dir: Vec3) -> Self {
-        Self { ori, dir }
+        Self { ori, dir, wavelength: None, time: 0.0 }
+    }
+
+    // carry the per-path attributes of the parent ray over to a scattered ray
+    pub fn inherit(&mut self, parent: &Ray) {
+        self.wavelength = parent.wavelength;
+        self.time = parent.time;
     }
 
     pub fn at(&self, t: f64) -> Point3 {
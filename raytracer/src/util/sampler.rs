@@ -0,0 +1,161 @@
+// Random numbers for rendering. Before every sample of a pixel the sampler is told which
+// pixel and sample index comes next, and it then hands out the dimensions of that sample
+// in the order the integrator asks for them: pixel position, lens, time, wavelength and
+// the scattering at every bounce. Everything only depends on the seed, not on how the
+// work was spread over threads.
+//
+// Independent draws every number at random. The other samplers spread the samples of a
+// pixel evenly over each dimension (or pair of dimensions), which gives less noise at
+// the same sample count.
+
+pub mod independent;
+pub mod stratified;
+pub mod halton;
+pub mod sobol;
+pub mod blue_noise;
+
+use std::sync::Arc;
+
+pub use independent::Independent;
+
+pub const DEFAULT_SEED: u64 = 0;
+
+pub trait Sampler: Send + Sync {
+    // start sample `index` of pixel (i, j), the following calls return its dimensions in order
+    fn start_pixel_sample(&mut self, i: u32, j: u32, index: u32);
+
+    // uniform in [0, 1)
+    fn next_f64(&mut self) -> f64;
+
+    // a pair of dimensions that is well distributed as a whole, e.g. for a point on the lens
+    fn next_2d(&mut self) -> (f64, f64);
+
+    // uniform integer in [0, n)
+    fn next_below(&mut self, n: u32) -> u32 {
+        ((self.next_f64() * n as f64) as u32).min(n - 1)
+    }
+
+    // a fresh sampler of the same kind, e.g. one per tile
+    fn clone_box(&self) -> Box<dyn Sampler>;
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SamplerKind {
+    Independent,
+    Stratified,
+    Halton,
+    Sobol,
+    BlueNoise,
+}
+
+impl SamplerKind {
+    pub const ALL: [SamplerKind; 5] = [
+        SamplerKind::Independent,
+        SamplerKind::Stratified,
+        SamplerKind::Halton,
+        SamplerKind::Sobol,
+        SamplerKind::BlueNoise,
+    ];
+
+    pub fn name(&self) -> &'static str {
+        match self {
+            SamplerKind::Independent => "independent",
+            SamplerKind::Stratified => "stratified",
+            SamplerKind::Halton => "halton",
+            SamplerKind::Sobol => "sobol",
+            SamplerKind::BlueNoise => "bluenoise",
+        }
+    }
+
+    pub fn from_name(name: &str) -> Result<Self, String> {
+        Self::ALL
+            .iter()
+            .find(|kind| kind.name() == name)
+            .copied()
+            .ok_or_else(|| format!("unknown sampler: {}", name))
+    }
+
+    // the stratified sampler splits every dimension into samples_per_pixel strata
+    pub fn build(&self, seed: u64, samples_per_pixel: u32) -> Box<dyn Sampler> {
+        match self {
+            SamplerKind::Independent => Box::new(Independent::new(seed)),
+            SamplerKind::Stratified => Box::new(stratified::Stratified::new(seed, samples_per_pixel)),
+            SamplerKind::Halton => Box::new(halton::Halton::new(seed)),
+            SamplerKind::Sobol => Box::new(sobol::Sobol::new(seed)),
+            SamplerKind::BlueNoise => {
+                let mask = blue_noise::BlueNoiseMask::new(blue_noise::MASK_SIZE, seed);
+                Box::new(blue_noise::BlueNoise::new(seed, Arc::new(mask)))
+            }
+        }
+    }
+}
+
+// splitmix64 finalizer, spreads neighbouring inputs over the whole state space
+fn mix(mut z: u64) -> u64 {
+    z = z.wrapping_add(0x9e3779b97f4a7c15);
+    z = (z ^ (z >> 30)).wrapping_mul(0xbf58476d1ce4e5b9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94d049bb133111eb);
+    z ^ (z >> 31)
+}
+
+// a different hash for every pixel of every seed
+fn pixel_hash(seed: u64, i: u32, j: u32) -> u64 {
+    mix(mix(seed) ^ ((j as u64) << 32 | i as u64))
+}
+
+fn to_unit(x: u32) -> f64 {
+    x as f64 / 4294967296.0
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // mean squared error of estimating the integral of a smooth function over the
+    // pixel with 64 samples, averaged over many pixels
+    fn integration_error(kind: SamplerKind) -> f64 {
+        let n = 64;
+        let exact = (1.0 - 1.0f64.cos()) * (1.0 - 1.0f64.cos());
+        let mut sampler = kind.build(3, n);
+        let mut error = 0.0;
+        for j in 0..16 {
+            for i in 0..16 {
+                let mut sum = 0.0;
+                for k in 0..n {
+                    sampler.start_pixel_sample(i, j, k);
+                    // skip a few dimensions so this is not just the first pair
+                    sampler.next_2d();
+                    sampler.next_f64();
+                    let (u, v) = sampler.next_2d();
+                    assert!((0.0..1.0).contains(&u) && (0.0..1.0).contains(&v));
+                    sum += u.sin() * v.sin();
+                }
+                error += (sum / n as f64 - exact).powi(2);
+            }
+        }
+        error / 256.0
+    }
+
+    #[test]
+    fn test_well_distributed_beat_independent() {
+        let independent = integration_error(SamplerKind::Independent);
+        for &kind in &SamplerKind::ALL[1..] {
+            let error = integration_error(kind);
+            assert!(error < independent / 4.0, "{:?}: {} vs {}", kind, error, independent);
+        }
+    }
+
+    #[test]
+    fn test_reproducible() {
+        for &kind in &SamplerKind::ALL {
+            let mut a = kind.build(7, 16);
+            let mut b = a.clone_box();
+            let mut values = Vec::new();
+            for sampler in [&mut a, &mut b].iter_mut() {
+                sampler.start_pixel_sample(3, 4, 5);
+                values.push((sampler.next_2d(), sampler.next_f64()));
+            }
+            assert_eq!(values[0], values[1], "{:?}", kind);
+        }
+    }
+}
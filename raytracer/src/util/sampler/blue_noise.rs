@@ -0,0 +1,246 @@
+use std::sync::Arc;
+
+use super::sobol::nested_uniform_scramble;
+use super::{mix, Independent, Sampler};
+
+pub const MASK_SIZE: usize = 64;
+
+// 1 / golden ratio and the R2 constants 1 / g, 1 / g^2 with g the plastic number
+const R1: f64 = 0.618_033_988_749_894_9;
+const R2: (f64, f64) = (0.754_877_666_246_692_8, 0.569_840_290_998_053_3);
+
+// A tileable blue noise mask made with void and cluster (Ulichney 1993): every value in
+// [0, 1) appears once, and pixels with similar values are far apart. Used as per pixel
+// offsets, the error of neighbouring pixels is uncorrelated and looks like fine grain
+// instead of blotches.
+#[derive(Debug)]
+pub struct BlueNoiseMask {
+    size: usize,
+    values: Vec<f64>,
+}
+
+// a binary pattern on the torus with the gaussian weighted density of points around every pixel
+#[derive(Clone)]
+struct Pattern {
+    size: usize,
+    kernel: Vec<f64>,
+    points: Vec<bool>,
+    energy: Vec<f64>,
+}
+
+impl Pattern {
+    fn toggle(&mut self, p: usize) {
+        self.points[p] = !self.points[p];
+        let sign = if self.points[p] { 1.0 } else { -1.0 };
+        let size = self.size;
+        let (px, py) = (p % size, p / size);
+        for qy in 0..size {
+            let dy = (qy + size - py) % size;
+            for qx in 0..size {
+                let dx = (qx + size - px) % size;
+                self.energy[qy * size + qx] += sign * self.kernel[dy * size + dx];
+            }
+        }
+    }
+
+    // the point with the most points around it
+    fn tightest_cluster(&self) -> usize {
+        self.extreme(true, |a, b| a > b)
+    }
+
+    // the empty pixel with the fewest points around it
+    fn largest_void(&self) -> usize {
+        self.extreme(false, |a, b| a < b)
+    }
+
+    fn extreme(&self, point: bool, better: impl Fn(f64, f64) -> bool) -> usize {
+        let mut best: Option<usize> = None;
+        for (p, &e) in self.energy.iter().enumerate() {
+            if self.points[p] != point {
+                continue;
+            }
+            match best {
+                Some(b) if !better(e, self.energy[b]) => {}
+                _ => best = Some(p),
+            }
+        }
+        best.expect("pattern has no such pixel")
+    }
+}
+
+impl BlueNoiseMask {
+    pub fn new(size: usize, seed: u64) -> Self {
+        let n = size * size;
+        let sigma = 1.5;
+        let mut kernel = vec![0.0; n];
+        for dy in 0..size {
+            for dx in 0..size {
+                let (x, y) = (dx.min(size - dx) as f64, dy.min(size - dy) as f64);
+                kernel[dy * size + dx] = (-(x * x + y * y) / (2.0 * sigma * sigma)).exp();
+            }
+        }
+        let mut pattern = Pattern { size, kernel, points: vec![false; n], energy: vec![0.0; n] };
+
+        // start from a tenth of the pixels at random
+        let mut rng = Independent::new(seed);
+        let initial = (n / 10).max(1);
+        let mut count = 0;
+        while count < initial {
+            let p = rng.next_below(n as u32) as usize;
+            if !pattern.points[p] {
+                pattern.toggle(p);
+                count += 1;
+            }
+        }
+        // move points from the tightest cluster to the largest void until they stay put
+        for _ in 0..n {
+            let cluster = pattern.tightest_cluster();
+            pattern.toggle(cluster);
+            let void = pattern.largest_void();
+            pattern.toggle(void);
+            if void == cluster {
+                break;
+            }
+        }
+
+        // rank the initial points by removing the tightest cluster first, then rank the
+        // remaining pixels by filling the largest void first
+        let mut rank = vec![0; n];
+        let mut removing = pattern.clone();
+        for r in (0..initial).rev() {
+            let p = removing.tightest_cluster();
+            removing.toggle(p);
+            rank[p] = r;
+        }
+        for r in initial..n {
+            let p = pattern.largest_void();
+            pattern.toggle(p);
+            rank[p] = r;
+        }
+        Self {
+            size,
+            values: rank.iter().map(|&r| (r as f64 + 0.5) / n as f64).collect(),
+        }
+    }
+
+    pub fn value(&self, i: usize, j: usize) -> f64 {
+        self.values[(j % self.size) * self.size + i % self.size]
+    }
+}
+
+fn fract(x: f64) -> f64 {
+    x - x.floor()
+}
+
+// Blue noise dithered low discrepancy samples: every dimension of a pixel starts at the
+// mask value under the pixel, with the mask shifted by a random offset per dimension, and
+// the samples of the pixel walk from there along the golden ratio (R1) or, for pairs of
+// dimensions, the R2 sequence. Each dimension shuffles the sample index with its own
+// nested uniform scramble, else all dimensions would take the same steps and be shifted
+// copies of each other. The shuffle maps every power of two prefix of the indices onto a
+// run of consecutive ones, which the R1 and R2 sequences do not mind.
+#[derive(Clone, Debug)]
+pub struct BlueNoise {
+    seed: u64,
+    mask: Arc<BlueNoiseMask>,
+    pixel: (usize, usize),
+    index: u32,
+    dimension: u64,
+}
+
+impl BlueNoise {
+    pub fn new(seed: u64, mask: Arc<BlueNoiseMask>) -> Self {
+        Self { seed, mask, pixel: (0, 0), index: 0, dimension: 0 }
+    }
+
+    // the start of the next dimension and the sample index shuffled for it
+    fn next_dimension(&mut self) -> (f64, f64) {
+        let hash = mix(mix(self.seed) ^ self.dimension);
+        self.dimension += 1;
+        let (dx, dy) = ((hash & 0xffff_ffff) as usize, (hash >> 32) as usize);
+        let offset = self.mask.value(self.pixel.0 + dx % self.mask.size, self.pixel.1 + dy % self.mask.size);
+        let index = nested_uniform_scramble(self.index, mix(hash) as u32);
+        (offset, index as f64)
+    }
+}
+
+impl Sampler for BlueNoise {
+    fn start_pixel_sample(&mut self, i: u32, j: u32, index: u32) {
+        self.pixel = (i as usize, j as usize);
+        self.index = index;
+        self.dimension = 0;
+    }
+
+    fn next_f64(&mut self) -> f64 {
+        let (offset, index) = self.next_dimension();
+        fract(offset + index * R1)
+    }
+
+    fn next_2d(&mut self) -> (f64, f64) {
+        let (u, index) = self.next_dimension();
+        let (v, _) = self.next_dimension();
+        (fract(u + index * R2.0), fract(v + index * R2.1))
+    }
+
+    fn clone_box(&self) -> Box<dyn Sampler> {
+        Box::new(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mask_is_permutation_without_clumps() {
+        let size = 16;
+        let mask = BlueNoiseMask::new(size, 4);
+        let mut ranks: Vec<usize> = mask.values.iter().map(|v| (v * (size * size) as f64) as usize).collect();
+        ranks.sort_unstable();
+        assert!(ranks.iter().enumerate().all(|(k, &r)| k == r));
+
+        // the darkest eighth of the pixels never touch each other
+        for j in 0..size {
+            for i in 0..size {
+                if mask.value(i, j) < 0.125 {
+                    assert!(mask.value(i + 1, j) >= 0.125 && mask.value(i, j + 1) >= 0.125);
+                }
+            }
+        }
+    }
+    #[test]
+    fn test_dimensions_are_independent() {
+        let mut sampler = BlueNoise::new(3, Arc::new(BlueNoiseMask::new(16, 3)));
+        let n = 256;
+        let samples: Vec<(f64, f64)> = (0..n)
+            .map(|index| {
+                sampler.start_pixel_sample(5, 7, index);
+                (sampler.next_f64(), sampler.next_f64())
+            })
+            .collect();
+        // without a shuffle per dimension the second one is the first shifted by a constant
+        let shift = |&(x, y): &(f64, f64)| fract(y - x);
+        let mut shifts: Vec<f64> = samples.iter().map(shift).collect();
+        shifts.sort_by(|a, b| a.partial_cmp(b).unwrap());
+        assert!(shifts[n as usize - 1] - shifts[0] > 0.5);
+
+        let mean = |f: fn(&(f64, f64)) -> f64| samples.iter().map(f).sum::<f64>() / n as f64;
+        let (mx, my) = (mean(|s| s.0), mean(|s| s.1));
+        let (mut covariance, mut vx, mut vy) = (0.0, 0.0, 0.0);
+        for &(x, y) in &samples {
+            covariance += (x - mx) * (y - my);
+            vx += (x - mx) * (x - mx);
+            vy += (y - my) * (y - my);
+        }
+        let correlation = covariance / (vx * vy).sqrt();
+        assert!(correlation.abs() < 0.2, "correlation {}", correlation);
+
+        // each dimension on its own still covers every eighth of [0, 1) in 8 samples
+        let mut strata = [[0; 8]; 2];
+        for &(x, y) in &samples[..8] {
+            strata[0][(x * 8.0) as usize] += 1;
+            strata[1][(y * 8.0) as usize] += 1;
+        }
+        assert!(strata.iter().all(|s| s.iter().filter(|&&c| c > 0).count() >= 6));
+    }
+}
@@ -0,0 +1,89 @@
+use super::{mix, pixel_hash, to_unit, Independent, Sampler};
+
+const PRIMES: [u32; 32] = [
+    2, 3, 5, 7, 11, 13, 17, 19, 23, 29, 31, 37, 41, 43, 47, 53, 59, 61, 67, 71, 73, 79, 83, 89, 97, 101, 103, 107,
+    109, 113, 127, 131,
+];
+
+// The Halton sequence: dimension d of sample k is the radical inverse of k in the d-th
+// prime base. All pixels walk the same sequence, shifted by a random offset per pixel and
+// dimension (Cranley-Patterson rotation) so neighbouring pixels do not repeat each other.
+// Dimensions past the prime table are drawn at random.
+#[derive(Clone, Debug)]
+pub struct Halton {
+    seed: u64,
+    pixel: u64,
+    index: u32,
+    dimension: usize,
+    fallback: Independent,
+}
+
+impl Halton {
+    pub fn new(seed: u64) -> Self {
+        Self {
+            seed,
+            pixel: 0,
+            index: 0,
+            dimension: 0,
+            fallback: Independent::new(seed),
+        }
+    }
+}
+
+// mirror the digits of n in the given base around the decimal point
+pub fn radical_inverse(base: u32, mut n: u32) -> f64 {
+    let inv_base = 1.0 / base as f64;
+    let mut factor = inv_base;
+    let mut result = 0.0;
+    while n > 0 {
+        result += (n % base) as f64 * factor;
+        n /= base;
+        factor *= inv_base;
+    }
+    result
+}
+
+impl Sampler for Halton {
+    fn start_pixel_sample(&mut self, i: u32, j: u32, index: u32) {
+        self.pixel = pixel_hash(self.seed, i, j);
+        self.index = index;
+        self.dimension = 0;
+        self.fallback.start_pixel_sample(i, j, index);
+    }
+
+    fn next_f64(&mut self) -> f64 {
+        let dimension = self.dimension;
+        self.dimension += 1;
+        if dimension >= PRIMES.len() {
+            return self.fallback.next_f64();
+        }
+        let shift = to_unit(mix(self.pixel ^ dimension as u64) as u32);
+        let u = radical_inverse(PRIMES[dimension], self.index) + shift;
+        if u >= 1.0 {
+            u - 1.0
+        } else {
+            u
+        }
+    }
+
+    fn next_2d(&mut self) -> (f64, f64) {
+        let u = self.next_f64();
+        (u, self.next_f64())
+    }
+
+    fn clone_box(&self) -> Box<dyn Sampler> {
+        Box::new(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_radical_inverse() {
+        assert_eq!(radical_inverse(2, 1), 0.5);
+        assert_eq!(radical_inverse(2, 6), 0.375);
+        assert!((radical_inverse(3, 5) - 7.0 / 9.0).abs() < 1e-12);
+    }
+}
@@ -0,0 +1,90 @@
+use super::{mix, pixel_hash, to_unit, Sampler};
+
+// Independent uniform random numbers from PCG32 (O'Neill 2014): 64 bit LCG state with a
+// permuted 32 bit output. Every sample of every pixel gets its own stream derived from
+// (seed, pixel, sample index).
+#[derive(Clone, Debug)]
+pub struct Independent {
+    seed: u64,
+    state: u64,
+}
+
+const MULTIPLIER: u64 = 6364136223846793005;
+const INCREMENT: u64 = 1442695040888963407;
+
+impl Independent {
+    pub fn new(seed: u64) -> Self {
+        let mut sampler = Self { seed, state: 0 };
+        sampler.reseed(mix(seed));
+        sampler
+    }
+
+    fn reseed(&mut self, seed: u64) {
+        self.state = seed;
+        self.next_u32();
+    }
+
+    pub fn next_u32(&mut self) -> u32 {
+        let old = self.state;
+        self.state = old.wrapping_mul(MULTIPLIER).wrapping_add(INCREMENT);
+        let xorshifted = (((old >> 18) ^ old) >> 27) as u32;
+        xorshifted.rotate_right((old >> 59) as u32)
+    }
+}
+
+impl Sampler for Independent {
+    fn start_pixel_sample(&mut self, i: u32, j: u32, index: u32) {
+        self.reseed(mix(pixel_hash(self.seed, i, j) ^ index as u64));
+    }
+
+    fn next_f64(&mut self) -> f64 {
+        to_unit(self.next_u32())
+    }
+
+    fn next_2d(&mut self) -> (f64, f64) {
+        let u = self.next_f64();
+        (u, self.next_f64())
+    }
+
+    fn next_below(&mut self, n: u32) -> u32 {
+        ((self.next_u32() as u64 * n as u64) >> 32) as u32
+    }
+
+    fn clone_box(&self) -> Box<dyn Sampler> {
+        Box::new(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::util::sampler::DEFAULT_SEED;
+
+    #[test]
+    fn test_reproducible() {
+        let mut a = Independent::new(7);
+        let mut b = Independent::new(7);
+        a.start_pixel_sample(3, 4, 5);
+        b.start_pixel_sample(3, 4, 5);
+        let xs: Vec<u32> = (0..8).map(|_| a.next_u32()).collect();
+        let ys: Vec<u32> = (0..8).map(|_| b.next_u32()).collect();
+        b.start_pixel_sample(4, 3, 5);
+        let zs: Vec<u32> = (0..8).map(|_| b.next_u32()).collect();
+        assert_eq!(xs, ys);
+        assert_ne!(xs, zs);
+    }
+
+    #[test]
+    fn test_uniform() {
+        let mut sampler = Independent::new(DEFAULT_SEED);
+        let n = 100000;
+        let mut buckets = [0; 10];
+        for _ in 0..n {
+            let u = sampler.next_f64();
+            assert!((0.0..1.0).contains(&u));
+            buckets[(u * 10.0) as usize] += 1;
+            assert!(sampler.next_below(3) < 3);
+        }
+        assert!(buckets.iter().all(|&count| (count as f64 - n as f64 / 10.0).abs() < 500.0));
+    }
+}
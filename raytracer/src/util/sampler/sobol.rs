@@ -0,0 +1,101 @@
+use super::{mix, pixel_hash, to_unit, Sampler};
+
+// Owen scrambled Sobol points (Burley 2020, "Practical Hash-based Owen Scrambling").
+// Every pair of dimensions takes the first two Sobol dimensions, a (0, 2) sequence, with
+// the sample index shuffled and both coordinates scrambled by a hash of the pixel and the
+// dimension. Every power of two prefix stays stratified in both dimensions and their
+// product, while different pixels and dimensions are independent of each other.
+#[derive(Clone, Debug)]
+pub struct Sobol {
+    seed: u64,
+    pixel: u64,
+    index: u32,
+    dimension: u64,
+}
+
+impl Sobol {
+    pub fn new(seed: u64) -> Self {
+        Self { seed, pixel: 0, index: 0, dimension: 0 }
+    }
+
+    fn next_seed(&mut self) -> u32 {
+        let seed = mix(self.pixel ^ self.dimension) as u32;
+        self.dimension += 1;
+        seed
+    }
+}
+
+// the first two dimensions of the Sobol sequence as 32 bit fractions
+fn sobol_02(index: u32) -> (u32, u32) {
+    let mut y = 0;
+    let mut v: u32 = 1 << 31;
+    let mut n = index;
+    while n != 0 {
+        if n & 1 != 0 {
+            y ^= v;
+        }
+        n >>= 1;
+        v ^= v >> 1;
+    }
+    (index.reverse_bits(), y)
+}
+
+fn laine_karras_permutation(mut x: u32, seed: u32) -> u32 {
+    x ^= x.wrapping_mul(0x3d20adea);
+    x = x.wrapping_add(seed);
+    x = x.wrapping_mul((seed >> 16) | 1);
+    x ^= x.wrapping_mul(0x05526c56);
+    x ^= x.wrapping_mul(0x53a22864);
+    x
+}
+
+// Owen scrambling: flipping a digit depends only on the digits above it
+pub(super) fn nested_uniform_scramble(x: u32, seed: u32) -> u32 {
+    laine_karras_permutation(x.reverse_bits(), seed).reverse_bits()
+}
+
+impl Sampler for Sobol {
+    fn start_pixel_sample(&mut self, i: u32, j: u32, index: u32) {
+        self.pixel = pixel_hash(self.seed, i, j);
+        self.index = index;
+        self.dimension = 0;
+    }
+
+    fn next_f64(&mut self) -> f64 {
+        self.next_2d().0
+    }
+
+    fn next_2d(&mut self) -> (f64, f64) {
+        let seed = self.next_seed();
+        let (x, y) = sobol_02(nested_uniform_scramble(self.index, seed));
+        let x = nested_uniform_scramble(x, mix(seed as u64) as u32);
+        let y = nested_uniform_scramble(y, mix(seed as u64 ^ 1) as u32);
+        (to_unit(x), to_unit(y))
+    }
+
+    fn clone_box(&self) -> Box<dyn Sampler> {
+        Box::new(self.clone())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_power_of_two_prefix_is_stratified() {
+        let mut sampler = Sobol::new(9);
+        // 16 points: one in every 4 x 4 cell and every 1 x 16 and 16 x 1 strip
+        let mut cells = [0; 16];
+        let mut columns = [0; 16];
+        let mut rows = [0; 16];
+        for k in 0..16 {
+            sampler.start_pixel_sample(1, 2, k);
+            let (u, v) = sampler.next_2d();
+            cells[(v * 4.0) as usize * 4 + (u * 4.0) as usize] += 1;
+            columns[(u * 16.0) as usize] += 1;
+            rows[(v * 16.0) as usize] += 1;
+        }
+        assert!(cells.iter().chain(&columns).chain(&rows).all(|&c| c == 1));
+    }
+}
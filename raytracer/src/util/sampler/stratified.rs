@@ -0,0 +1,138 @@
+use super::{mix, pixel_hash, Independent, Sampler};
+
+// Jittered stratification: every dimension is split into samples_per_pixel strata and
+// every sample of a pixel lands in a different one, pairs of dimensions are split into a
+// grid. Each pixel and dimension visits the strata in its own random order, so the
+// dimensions are not correlated with each other. Samples past samples_per_pixel, e.g.
+// after resuming with a higher count, are drawn at random.
+#[derive(Clone, Debug)]
+pub struct Stratified {
+    seed: u64,
+    samples: u32,
+    pixel: u64,
+    index: u32,
+    dimension: u64,
+    jitter: Independent,
+}
+
+impl Stratified {
+    pub fn new(seed: u64, samples_per_pixel: u32) -> Self {
+        assert!(samples_per_pixel > 0, "stratified sampling needs at least one sample");
+        Self {
+            seed,
+            samples: samples_per_pixel,
+            pixel: 0,
+            index: 0,
+            dimension: 0,
+            jitter: Independent::new(seed),
+        }
+    }
+
+    // stratum of the current sample among n strata for the next dimension
+    fn next_stratum(&mut self, n: u32) -> Option<u32> {
+        let hash = mix(self.pixel ^ self.dimension);
+        self.dimension += 1;
+        if self.index >= n {
+            return None;
+        }
+        Some(permutation_element(self.index, n, hash as u32))
+    }
+}
+
+impl Sampler for Stratified {
+    fn start_pixel_sample(&mut self, i: u32, j: u32, index: u32) {
+        self.pixel = pixel_hash(self.seed, i, j);
+        self.index = index;
+        self.dimension = 0;
+        self.jitter.start_pixel_sample(i, j, index);
+    }
+
+    fn next_f64(&mut self) -> f64 {
+        match self.next_stratum(self.samples) {
+            Some(stratum) => (stratum as f64 + self.jitter.next_f64()) / self.samples as f64,
+            None => self.jitter.next_f64(),
+        }
+    }
+
+    // the largest nx x ny grid with nx * ny <= samples and nx, ny as equal as possible
+    fn next_2d(&mut self) -> (f64, f64) {
+        let nx = (self.samples as f64).sqrt() as u32;
+        let ny = self.samples / nx;
+        match self.next_stratum(nx * ny) {
+            Some(stratum) => {
+                let (dx, dy) = self.jitter.next_2d();
+                (((stratum % nx) as f64 + dx) / nx as f64, ((stratum / nx) as f64 + dy) / ny as f64)
+            }
+            None => self.jitter.next_2d(),
+        }
+    }
+
+    fn clone_box(&self) -> Box<dyn Sampler> {
+        Box::new(self.clone())
+    }
+}
+
+// Element i of a random permutation of 0..n chosen by `seed`, without building the
+// permutation (Kensler 2013, "Correlated Multi-Jittered Sampling").
+pub fn permutation_element(mut i: u32, n: u32, seed: u32) -> u32 {
+    let mut w = n - 1;
+    w |= w >> 1;
+    w |= w >> 2;
+    w |= w >> 4;
+    w |= w >> 8;
+    w |= w >> 16;
+    loop {
+        i ^= seed;
+        i = i.wrapping_mul(0xe170893d);
+        i ^= seed >> 16;
+        i ^= (i & w) >> 4;
+        i ^= seed >> 8;
+        i = i.wrapping_mul(0x0929eb3f);
+        i ^= seed >> 23;
+        i ^= (i & w) >> 1;
+        i = i.wrapping_mul(1 | seed >> 27);
+        i = i.wrapping_mul(0x6935fa69);
+        i ^= (i & w) >> 11;
+        i = i.wrapping_mul(0x74dcb303);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0x9e501cc3);
+        i ^= (i & w) >> 2;
+        i = i.wrapping_mul(0xc860a3df);
+        i &= w;
+        i ^= i >> 5;
+        if i < n {
+            return (i.wrapping_add(seed)) % n;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_permutation() {
+        for &n in &[1, 2, 7, 16, 100] {
+            let mut seen = vec![false; n as usize];
+            for i in 0..n {
+                seen[permutation_element(i, n, 12345) as usize] = true;
+            }
+            assert!(seen.iter().all(|&s| s));
+        }
+    }
+
+    #[test]
+    fn test_one_sample_per_stratum() {
+        let mut sampler = Stratified::new(1, 16);
+        let mut rows = [0; 16];
+        let mut cells = [0; 16];
+        for k in 0..16 {
+            sampler.start_pixel_sample(2, 5, k);
+            let (u, v) = sampler.next_2d();
+            cells[(v * 4.0) as usize * 4 + (u * 4.0) as usize] += 1;
+            rows[(sampler.next_f64() * 16.0) as usize] += 1;
+        }
+        assert!(cells.iter().all(|&c| c == 1));
+        assert!(rows.iter().all(|&c| c == 1));
+    }
+}
@@ -0,0 +1,138 @@
+use crate::util::vec3::{Color, Vec3};
+
+pub const LAMBDA_MIN: f64 = 380.0;
+pub const LAMBDA_MAX: f64 = 780.0;
+
+// integral of the y matching function over [LAMBDA_MIN, LAMBDA_MAX]
+const CIE_Y_INTEGRAL: f64 = 106.919735;
+// linear sRGB of the equal energy spectrum, used to white balance the output
+const EQUAL_ENERGY_RGB: [f64; 3] = [1.200536, 0.949666, 0.907829];
+
+// Smits' basis spectra for RGB upsampling, 10 bins over [380, 720]
+const SMITS_LAMBDA_MAX: f64 = 720.0;
+const SMITS_WHITE: [f64; 10] = [1.0000, 1.0000, 0.9999, 0.9993, 0.9992, 0.9998, 1.0000, 1.0000, 1.0000, 1.0000];
+const SMITS_CYAN: [f64; 10] = [0.9710, 0.9426, 1.0007, 1.0007, 1.0007, 1.0007, 0.1564, 0.0000, 0.0000, 0.0000];
+const SMITS_MAGENTA: [f64; 10] = [1.0000, 1.0000, 0.9685, 0.2229, 0.0000, 0.0458, 0.8369, 1.0000, 1.0000, 0.9959];
+const SMITS_YELLOW: [f64; 10] = [0.0001, 0.0000, 0.1088, 0.6651, 1.0000, 1.0000, 0.9996, 0.9586, 0.9685, 0.9840];
+const SMITS_RED: [f64; 10] = [0.1012, 0.0515, 0.0000, 0.0000, 0.0000, 0.0000, 0.8325, 1.0149, 1.0149, 1.0149];
+const SMITS_GREEN: [f64; 10] = [0.0000, 0.0000, 0.0273, 0.7937, 1.0000, 0.9418, 0.1719, 0.0000, 0.0000, 0.0025];
+const SMITS_BLUE: [f64; 10] = [1.0000, 1.0000, 0.8916, 0.3323, 0.0000, 0.0000, 0.0003, 0.0369, 0.0483, 0.0496];
+
+// map a uniform random number in [0, 1) to a wavelength in nanometers
+pub fn sample_wavelength(u: f64) -> f64 {
+    LAMBDA_MIN + (LAMBDA_MAX - LAMBDA_MIN) * u
+}
+
+// piecewise gaussian used by the multi-lobe fit of Wyman et al. 2013
+fn lobe(lambda: f64, mu: f64, sigma_1: f64, sigma_2: f64) -> f64 {
+    let sigma = if lambda < mu { sigma_1 } else { sigma_2 };
+    let t = (lambda - mu) / sigma;
+    (-0.5 * t * t).exp()
+}
+
+// CIE 1931 color matching functions, returned as (x, y, z)
+pub fn cie_xyz(lambda: f64) -> Vec3 {
+    let x = 1.056 * lobe(lambda, 599.8, 37.9, 31.0) + 0.362 * lobe(lambda, 442.0, 16.0, 26.7)
+        - 0.065 * lobe(lambda, 501.1, 20.4, 26.2);
+    let y = 0.821 * lobe(lambda, 568.8, 46.9, 40.5) + 0.286 * lobe(lambda, 530.9, 16.3, 31.1);
+    let z = 1.217 * lobe(lambda, 437.0, 11.8, 36.0) + 0.681 * lobe(lambda, 459.0, 26.0, 13.8);
+    Vec3::new(x, y, z)
+}
+
+pub fn xyz_to_linear_srgb(xyz: Vec3) -> Color {
+    Color::new(
+        3.2404542 * xyz.x - 1.5371385 * xyz.y - 0.4985314 * xyz.z,
+        -0.9692660 * xyz.x + 1.8760108 * xyz.y + 0.0415560 * xyz.z,
+        0.0556434 * xyz.x - 0.2040259 * xyz.y + 1.0572252 * xyz.z,
+    )
+}
+
+// Linear sRGB contribution of a unit radiance sample at the given wavelength,
+// already divided by the pdf of uniform wavelength sampling, so that averaging
+// over samples converges to the color of the spectrum.
+pub fn wavelength_to_rgb(lambda: f64) -> Color {
+    let xyz = cie_xyz(lambda) * ((LAMBDA_MAX - LAMBDA_MIN) / CIE_Y_INTEGRAL);
+    let rgb = xyz_to_linear_srgb(xyz);
+    Color::new(
+        rgb.x / EQUAL_ENERGY_RGB[0],
+        rgb.y / EQUAL_ENERGY_RGB[1],
+        rgb.z / EQUAL_ENERGY_RGB[2],
+    )
+}
+
+fn smits_bin(basis: &[f64; 10], lambda: f64) -> f64 {
+    let t = (lambda - LAMBDA_MIN) / (SMITS_LAMBDA_MAX - LAMBDA_MIN) * 10.0;
+    let index = if t < 0.0 { 0 } else { (t as usize).min(9) };
+    basis[index]
+}
+
+// Evaluate the spectrum upsampled from an RGB triple (Smits 1999) at the given wavelength.
+// The method is linear in the input, so emission colors above 1.0 are supported as well.
+pub fn rgb_to_spectrum(color: Color, lambda: f64) -> f64 {
+    let (r, g, b) = (color.x, color.y, color.z);
+    let white = |v: f64| v * smits_bin(&SMITS_WHITE, lambda);
+    if r <= g && r <= b {
+        let mut value = white(r);
+        if g <= b {
+            value += (g - r) * smits_bin(&SMITS_CYAN, lambda);
+            value += (b - g) * smits_bin(&SMITS_BLUE, lambda);
+        } else {
+            value += (b - r) * smits_bin(&SMITS_CYAN, lambda);
+            value += (g - b) * smits_bin(&SMITS_GREEN, lambda);
+        }
+        value
+    } else if g <= r && g <= b {
+        let mut value = white(g);
+        if r <= b {
+            value += (r - g) * smits_bin(&SMITS_MAGENTA, lambda);
+            value += (b - r) * smits_bin(&SMITS_BLUE, lambda);
+        } else {
+            value += (b - g) * smits_bin(&SMITS_MAGENTA, lambda);
+            value += (r - b) * smits_bin(&SMITS_RED, lambda);
+        }
+        value
+    } else {
+        let mut value = white(b);
+        if r <= g {
+            value += (r - b) * smits_bin(&SMITS_YELLOW, lambda);
+            value += (g - r) * smits_bin(&SMITS_GREEN, lambda);
+        } else {
+            value += (g - b) * smits_bin(&SMITS_YELLOW, lambda);
+            value += (r - g) * smits_bin(&SMITS_RED, lambda);
+        }
+        value
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // integrate a spectrum over the visible range into linear sRGB
+    fn integrate(spectrum: impl Fn(f64) -> f64) -> Color {
+        let n = 4000;
+        let mut color = Color::zero();
+        for i in 0..n {
+            let lambda = sample_wavelength((i as f64 + 0.5) / n as f64);
+            color += wavelength_to_rgb(lambda) * spectrum(lambda);
+        }
+        color / n as f64
+    }
+
+    #[test]
+    fn test_equal_energy_is_white() {
+        let color = integrate(|_| 1.0);
+        assert!((color.x - 1.0).abs() < 1e-3);
+        assert!((color.y - 1.0).abs() < 1e-3);
+        assert!((color.z - 1.0).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_rgb_round_trip() {
+        let white = integrate(|lambda| rgb_to_spectrum(Color::ones(), lambda));
+        assert!((white - Color::ones()).length() < 0.02);
+
+        let red = integrate(|lambda| rgb_to_spectrum(Color::new(1.0, 0.0, 0.0), lambda));
+        assert!(red.x > 0.5 && red.x > red.y * 4.0 && red.x > red.z * 4.0);
+    }
+}
@@ -1,5 +1,6 @@
 use std::ops::{Add, AddAssign, Div, DivAssign, Mul, MulAssign, Neg, Sub, SubAssign};
-use rand::Rng;
+use std::f64::consts::PI;
+use crate::util::sampler::Sampler;
 
 #[derive(Clone, Copy, Debug, PartialEq)]
 pub struct Vec3 {
@@ -53,18 +54,30 @@ impl Vec3 {
         }
     }
 
-    pub fn random_unit() -> Self {
-        let mut rng = rand::thread_rng();
-        let p = Self {
-            x: rng.gen::<f64>() * 2.0 - 1.0,
-            y: rng.gen::<f64>() * 2.0 - 1.0,
-            z: rng.gen::<f64>() * 2.0 - 1.0,
-        };
-        if p.length() >= 1.0 {
-            Self::random_unit()
-        } else {
-            p
+    // Uniform point in the unit ball, a direction from a pair of dimensions and the radius
+    // from a third. No rejection, so every call uses the same dimensions of the sampler.
+    pub fn random_unit(sampler: &mut dyn Sampler) -> Self {
+        let (u, v) = sampler.next_2d();
+        let z = 1.0 - 2.0 * u;
+        let r = (1.0 - z * z).max(0.0).sqrt();
+        let phi = 2.0 * PI * v;
+        Self::new(r * phi.cos(), r * phi.sin(), z) * sampler.next_f64().cbrt()
+    }
+
+    // uniform point in the unit disk of the xy plane, concentric mapping (Shirley and Chiu)
+    // which keeps a well distributed square well distributed on the disk
+    pub fn random_in_unit_disk(sampler: &mut dyn Sampler) -> Self {
+        let (u, v) = sampler.next_2d();
+        let (a, b) = (2.0 * u - 1.0, 2.0 * v - 1.0);
+        if a == 0.0 && b == 0.0 {
+            return Self::zero();
         }
+        let (r, phi) = if a.abs() > b.abs() {
+            (a, PI / 4.0 * (b / a))
+        } else {
+            (b, PI / 2.0 - PI / 4.0 * (a / b))
+        };
+        Self::new(r * phi.cos(), r * phi.sin(), 0.0)
     }
 
     pub fn near_zero(&self) -> bool {
@@ -0,0 +1,183 @@
+use crate::util::vec3::Point3;
+use std::fs;
+
+// Scalar field sampled on a regular 3d grid, stored x fastest then y then z.
+pub struct VoxelGrid {
+    pub nx: usize,
+    pub ny: usize,
+    pub nz: usize,
+    pub data: Vec<f32>,
+}
+
+impl VoxelGrid {
+    pub fn new(nx: usize, ny: usize, nz: usize, data: Vec<f32>) -> Self {
+        assert!(nx > 0 && ny > 0 && nz > 0, "grid sizes must be positive");
+        assert_eq!(data.len(), nx * ny * nz, "voxel count does not match grid size");
+        Self { nx, ny, nz, data }
+    }
+
+    pub fn load(path: &str) -> Result<Self, String> {
+        let bytes = fs::read(path).map_err(|e| format!("failed to read {}: {}", path, e))?;
+        Self::from_nrrd(&bytes).map_err(|e| format!("{}: {}", path, e))
+    }
+
+    // Parse a NRRD file with attached raw data, e.g.
+    //   NRRD0004
+    //   type: float
+    //   dimension: 3
+    //   sizes: 64 64 64
+    //   encoding: raw
+    //   endian: little
+    // followed by an empty line and the voxels. 8 bit voxels are normalized to [0, 1].
+    pub fn from_nrrd(bytes: &[u8]) -> Result<Self, String> {
+        if !bytes.starts_with(b"NRRD") {
+            return Err("missing NRRD magic".to_string());
+        }
+        let mut offset = 0;
+        let mut value_type = String::new();
+        let mut sizes = vec![];
+        let mut encoding = "raw".to_string();
+        let mut big_endian = false;
+        loop {
+            let end = bytes[offset..]
+                .iter()
+                .position(|&b| b == b'\n')
+                .ok_or_else(|| "unterminated NRRD header".to_string())?;
+            let line = String::from_utf8_lossy(&bytes[offset..offset + end]).trim().to_string();
+            offset += end + 1;
+            if line.is_empty() {
+                break;
+            }
+            if line.starts_with('#') || line.starts_with("NRRD") {
+                continue;
+            }
+            let mut parts = line.splitn(2, ':');
+            let key = parts.next().unwrap_or("").trim();
+            let value = parts.next().unwrap_or("").trim();
+            match key {
+                "type" => value_type = value.to_string(),
+                "dimension" if value != "3" => return Err("only 3 dimensional grids are supported".to_string()),
+                "sizes" => {
+                    for size in value.split_whitespace() {
+                        sizes.push(size.parse::<usize>().map_err(|e| format!("invalid size: {}", e))?);
+                    }
+                }
+                "encoding" => encoding = value.to_string(),
+                "endian" => big_endian = value == "big",
+                _ => {}
+            }
+        }
+
+        if encoding != "raw" {
+            return Err(format!("unsupported encoding \"{}\"", encoding));
+        }
+        if sizes.len() != 3 {
+            return Err("expected three sizes".to_string());
+        }
+        if sizes.contains(&0) {
+            return Err("grid sizes must be positive".to_string());
+        }
+        let voxel_bytes = match value_type.as_str() {
+            "uchar" | "unsigned char" | "uint8" | "uint8_t" => 1,
+            "float" => 4,
+            other => return Err(format!("unsupported voxel type \"{}\"", other)),
+        };
+        // check the size before allocating anything for a possibly corrupt header
+        let count = sizes[0]
+            .checked_mul(sizes[1])
+            .and_then(|n| n.checked_mul(sizes[2]))
+            .filter(|n| n.checked_mul(voxel_bytes).is_some())
+            .ok_or_else(|| format!("grid of {}x{}x{} voxels is too large", sizes[0], sizes[1], sizes[2]))?;
+        let body = &bytes[offset..];
+        if body.len() < count * voxel_bytes {
+            return Err("not enough voxel data".to_string());
+        }
+        let data: Vec<f32> = if voxel_bytes == 1 {
+            body[..count].iter().map(|&b| b as f32 / 255.0).collect()
+        } else {
+            body.chunks_exact(4)
+                .take(count)
+                .map(|c| {
+                    let raw = [c[0], c[1], c[2], c[3]];
+                    if big_endian {
+                        f32::from_be_bytes(raw)
+                    } else {
+                        f32::from_le_bytes(raw)
+                    }
+                })
+                .collect()
+        };
+        Ok(Self::new(sizes[0], sizes[1], sizes[2], data))
+    }
+
+    pub fn max_value(&self) -> f64 {
+        self.data.iter().fold(0.0f32, |a, &b| a.max(b)) as f64
+    }
+
+    fn voxel(&self, x: usize, y: usize, z: usize) -> f64 {
+        self.data[(z * self.ny + y) * self.nx + x] as f64
+    }
+
+    // trilinear lookup at a point given in [0, 1]^3 grid coordinates, zero outside
+    pub fn sample(&self, p: Point3) -> f64 {
+        if p.x < 0.0 || p.x > 1.0 || p.y < 0.0 || p.y > 1.0 || p.z < 0.0 || p.z > 1.0 {
+            return 0.0;
+        }
+        // voxel values sit at the cell centers
+        let gx = (p.x * self.nx as f64 - 0.5).max(0.0);
+        let gy = (p.y * self.ny as f64 - 0.5).max(0.0);
+        let gz = (p.z * self.nz as f64 - 0.5).max(0.0);
+        let x0 = (gx as usize).min(self.nx - 1);
+        let y0 = (gy as usize).min(self.ny - 1);
+        let z0 = (gz as usize).min(self.nz - 1);
+        let x1 = (x0 + 1).min(self.nx - 1);
+        let y1 = (y0 + 1).min(self.ny - 1);
+        let z1 = (z0 + 1).min(self.nz - 1);
+        let (fx, fy, fz) = (gx - x0 as f64, gy - y0 as f64, gz - z0 as f64);
+
+        let lerp = |a: f64, b: f64, t: f64| a * (1.0 - t) + b * t;
+        let c00 = lerp(self.voxel(x0, y0, z0), self.voxel(x1, y0, z0), fx);
+        let c10 = lerp(self.voxel(x0, y1, z0), self.voxel(x1, y1, z0), fx);
+        let c01 = lerp(self.voxel(x0, y0, z1), self.voxel(x1, y0, z1), fx);
+        let c11 = lerp(self.voxel(x0, y1, z1), self.voxel(x1, y1, z1), fx);
+        lerp(lerp(c00, c10, fy), lerp(c01, c11, fy), fz)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_from_nrrd() {
+        let mut bytes = b"NRRD0004\n# test grid\ntype: float\ndimension: 3\nsizes: 2 1 1\nencoding: raw\nendian: little\n\n".to_vec();
+        bytes.extend_from_slice(&0.25f32.to_le_bytes());
+        bytes.extend_from_slice(&0.75f32.to_le_bytes());
+        let grid = VoxelGrid::from_nrrd(&bytes).unwrap();
+        assert_eq!((grid.nx, grid.ny, grid.nz), (2, 1, 1));
+        assert_eq!(grid.data, vec![0.25, 0.75]);
+        assert_eq!(grid.max_value(), 0.75);
+    }
+
+    #[test]
+    fn test_invalid_sizes() {
+        let header = |sizes: &str| format!("NRRD0004\ntype: uchar\ndimension: 3\nsizes: {}\n\n", sizes).into_bytes();
+        let error = |bytes: Vec<u8>| VoxelGrid::from_nrrd(&bytes).err().unwrap();
+        assert_eq!(error(header("4 0 4")), "grid sizes must be positive");
+        let huge = usize::MAX / 2;
+        assert_eq!(error(header(&format!("{} 4 1", huge))), format!("grid of {}x4x1 voxels is too large", huge));
+        assert_eq!(error(header("2 2 2")), "not enough voxel data");
+        let mut bytes = header("2 2 2");
+        bytes.extend_from_slice(&[255; 8]);
+        assert_eq!(VoxelGrid::from_nrrd(&bytes).unwrap().sample(Point3::new(0.5, 0.5, 0.5)), 1.0);
+    }
+
+    #[test]
+    fn test_sample() {
+        let grid = VoxelGrid::new(2, 1, 1, vec![0.0, 1.0]);
+        assert_eq!(grid.sample(Point3::new(0.25, 0.5, 0.5)), 0.0);
+        assert_eq!(grid.sample(Point3::new(0.5, 0.5, 0.5)), 0.5);
+        assert_eq!(grid.sample(Point3::new(0.75, 0.5, 0.5)), 1.0);
+        assert_eq!(grid.sample(Point3::new(1.5, 0.5, 0.5)), 0.0);
+    }
+}
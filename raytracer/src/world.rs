@@ -1,8 +1,18 @@
 use crate::hittable::Hittable;
+use crate::hittable::HitRecord;
+use crate::material::isotropic::Isotropic;
+use crate::util::interval::Interval;
+use crate::util::ray::Ray;
+use crate::util::vec3::{Color, Vec3};
+use crate::util::sampler::Sampler;
 
 
 pub struct World {
     pub hittables: Vec<Box<dyn Hittable>>,
+    // Material ID of the hittable at the same index, for objects made with copies of one
+    // material. Hittables with 0 or past the end have a material of their own.
+    pub material_ids: Vec<u32>,
+    pub fog: Option<Fog>, // homogeneous medium filling the whole scene
 }
 
 impl World {
@@ -12,4 +22,86 @@ impl World {
     {
         self.hittables.push(Box::<T>::new(obj));
     }
+
+    // objects added with the same material_id share it in the material AOV
+    pub fn add_hittable_with_material<T>(&mut self, obj: T, material_id: u32)
+    where
+        T: Hittable + 'static,
+    {
+        self.material_ids.resize(self.hittables.len(), 0);
+        self.material_ids.push(material_id);
+        self.add_hittable(obj);
+    }
+}
+
+// Atmospheric haze, rays scatter isotropically after an exponentially distributed distance.
+#[derive(Clone, Copy)]
+pub struct Fog {
+    pub density: f64,
+    pub phase_function: Isotropic,
+}
+
+impl Fog {
+    pub fn new(density: f64, albedo: Color) -> Self {
+        Self {
+            density,
+            phase_function: Isotropic::new(albedo),
+        }
+    }
+
+    // Returns a scattering event inside the fog if it happens before the surface hit.
+    // The fog only fills the space between surfaces, rays that miss everything leave it
+    // unscattered, otherwise no path would ever reach the background.
+    pub fn hit<'a>(
+        &'a self,
+        ray: &Ray,
+        rot: &Interval,
+        hit_record: Option<HitRecord<'a>>,
+        sampler: &mut dyn Sampler,
+    ) -> Option<HitRecord<'a>> {
+        let t_max = hit_record.as_ref()?.t;
+        if self.density <= 0.0 {
+            return hit_record;
+        }
+        let ray_length = ray.dir.length();
+        let distance = -(1.0 - sampler.next_f64()).ln() / self.density;
+        let t = rot.tmin + distance / ray_length;
+        if t >= t_max {
+            return hit_record;
+        }
+        Some(HitRecord::new(
+            ray.at(t),
+            t,
+            Vec3::new(1.0, 0.0, 0.0),
+            true,
+            &self.phase_function,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::material::diffusive::Diffusive;
+    use crate::util::sampler::Independent;
+    use crate::util::vec3::Point3;
+
+    #[test]
+    fn test_fog_free_path() {
+        let wall = Diffusive::new(Color::ones());
+        let surface = || Some(HitRecord::new(Point3::new(0.0, 0.0, -1e6), 1e6, Vec3::new(0.0, 0.0, 1.0), true, &wall));
+        let ray = Ray::new(Point3::zero(), Vec3::new(0.0, 0.0, -1.0));
+        let rot = Interval::new(0.0, f64::INFINITY);
+        let mut sampler = Independent::new(11);
+
+        let fog = Fog::new(0.25, Color::ones());
+        let n = 20000;
+        let total: f64 = (0..n).map(|_| fog.hit(&ray, &rot, surface(), &mut sampler).unwrap().t).sum();
+        assert!((total / n as f64 - 4.0).abs() < 0.1, "mean free path {}", total / n as f64);
+        // rays that miss everything leave the fog
+        assert!(fog.hit(&ray, &rot, None, &mut sampler).is_none());
+
+        let clear = Fog::new(0.0, Color::ones());
+        assert!((0..1000).all(|_| clear.hit(&ray, &rot, surface(), &mut sampler).unwrap().t == 1e6));
+    }
 }